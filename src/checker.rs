@@ -0,0 +1,439 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures::stream::{BoxStream, FuturesUnordered, StreamExt};
+use hickory_resolver::TokioAsyncResolver;
+use rustls::RootCertStore;
+use rustls::client::WebPkiServerVerifier;
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinHandle};
+use tokio_rustls::TlsConnector;
+use url::Url;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::config::{
+    AppConfig, DEFAULT_CONCURRENCY, DEFAULT_ERROR_DAYS, DEFAULT_PER_HOST_CONCURRENCY,
+    DEFAULT_WARNING_DAYS,
+};
+use crate::duration::HumanDuration;
+use crate::errors::{SslCheckError, TimeoutStage};
+use crate::probe::{
+    Timeouts, chain_verifier, fetch_certificates, system_resolver, tls_connector, verify_chain,
+};
+use crate::retry::RetryPolicy;
+use crate::targets::TargetOptions;
+use crate::{CertCheckResult, ChainCertificate, Finding, SslCheck};
+
+// --- Checks the certificates of a set of targets ---
+/// Cheap to clone; clones share the same connection resources and concurrency limits,
+/// so a checker can be kept and reused for any number of checks.
+#[derive(Clone)]
+pub struct Checker {
+    inner: Arc<CheckerInner>,
+}
+
+struct CheckerInner {
+    connector: TlsConnector,
+    resolver: TokioAsyncResolver,
+    chain_verifier: Option<Arc<WebPkiServerVerifier>>,
+    warning_threshold: Duration,
+    error_threshold: Duration,
+    timeouts: Timeouts,
+    retry_policy: RetryPolicy,
+    target_options: HashMap<String, TargetOptions>,
+    per_host_concurrency: usize,
+    // Checks wait for a slot for their host before taking one of the global slots,
+    // so a busy host doesn't hold up checks against other hosts
+    global_limit: Semaphore,
+    host_limits: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+// --- Builder for a Checker ---
+/// Starts with the same defaults as the command line tool.
+pub struct CheckerBuilder {
+    warning_threshold: Duration,
+    error_threshold: Duration,
+    trust_roots: Option<RootCertStore>,
+    timeouts: Timeouts,
+    retry_policy: RetryPolicy,
+    concurrency: usize,
+    per_host_concurrency: usize,
+    resolver: Option<TokioAsyncResolver>,
+    target_options: HashMap<String, TargetOptions>,
+}
+
+impl Default for CheckerBuilder {
+    fn default() -> Self {
+        Self {
+            warning_threshold: HumanDuration::from_days(DEFAULT_WARNING_DAYS).0,
+            error_threshold: HumanDuration::from_days(DEFAULT_ERROR_DAYS).0,
+            trust_roots: None,
+            timeouts: Timeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            concurrency: DEFAULT_CONCURRENCY,
+            per_host_concurrency: DEFAULT_PER_HOST_CONCURRENCY,
+            resolver: None,
+            target_options: HashMap::new(),
+        }
+    }
+}
+
+impl CheckerBuilder {
+    /// Certificates expiring sooner than this are reported as a warning.
+    pub fn warning_threshold(mut self, threshold: Duration) -> Self {
+        self.warning_threshold = threshold;
+        self
+    }
+
+    /// Certificates expiring sooner than this are reported as an error.
+    pub fn error_threshold(mut self, threshold: Duration) -> Self {
+        self.error_threshold = threshold;
+        self
+    }
+
+    /// Verifies each certificate chain against `roots`, marking certificates that
+    /// don't chain up to one of them as invalid. By default chains aren't verified,
+    /// only the certificate itself; see [`webpki_roots`](crate::probe::webpki_roots).
+    pub fn trust_roots(mut self, roots: RootCertStore) -> Self {
+        self.trust_roots = Some(roots);
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Maximum number of checks run at once. Zero is treated as one.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Maximum number of checks run at once against the same host and port. Zero
+    /// is treated as one.
+    pub fn per_host_concurrency(mut self, per_host_concurrency: usize) -> Self {
+        self.per_host_concurrency = per_host_concurrency;
+        self
+    }
+
+    /// Resolver used to look up hosts. Defaults to the system configuration.
+    pub fn resolver(mut self, resolver: TokioAsyncResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Overrides the timeouts and retries for a single target.
+    pub fn target_options(mut self, url: impl Into<String>, options: TargetOptions) -> Self {
+        self.target_options.insert(url.into(), options);
+        self
+    }
+
+    pub fn build(self) -> Result<Checker, rustls::Error> {
+        Ok(Checker {
+            inner: Arc::new(CheckerInner {
+                connector: tls_connector()?,
+                resolver: self.resolver.unwrap_or_else(system_resolver),
+                chain_verifier: self.trust_roots.map(chain_verifier).transpose()?,
+                warning_threshold: self.warning_threshold,
+                error_threshold: self.error_threshold,
+                timeouts: self.timeouts,
+                retry_policy: self.retry_policy,
+                target_options: self.target_options,
+                per_host_concurrency: self.per_host_concurrency.max(1),
+                global_limit: Semaphore::new(self.concurrency.max(1)),
+                host_limits: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+}
+
+impl Checker {
+    pub fn builder() -> CheckerBuilder {
+        CheckerBuilder::default()
+    }
+
+    /// Creates a checker using the thresholds, limits and per-target settings from `config`.
+    pub fn from_config(config: &AppConfig) -> Result<Self, rustls::Error> {
+        let builder = Self::builder()
+            .warning_threshold(config.warning_threshold)
+            .error_threshold(config.error_threshold)
+            .timeouts(config.timeouts)
+            .retry_policy(config.retry_policy)
+            .concurrency(config.concurrency)
+            .per_host_concurrency(config.per_host_concurrency);
+        config
+            .target_options
+            .iter()
+            .fold(builder, |builder, (url, options)| {
+                builder.target_options(url.clone(), options.clone())
+            })
+            .build()
+    }
+
+    /// Checks a single target, retrying it according to the retry policy.
+    pub async fn check_one(&self, url: impl Into<String>) -> SslCheck {
+        let url = url.into();
+        self.spawn_check(url.clone())
+            .await
+            .unwrap_or_else(|e| internal_error_check(&url, e))
+    }
+
+    /// Starts checking every target and returns the results as each one finishes,
+    /// which is not necessarily the order the targets were given in. The checks run
+    /// in the background, so they carry on even while the stream isn't being polled.
+    pub fn check_many<I, S>(&self, urls: I) -> BoxStream<'static, SslCheck>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        urls.into_iter()
+            .map(|url| {
+                let url = url.into();
+                let handle = self.spawn_check(url.clone());
+                async move {
+                    handle
+                        .await
+                        .unwrap_or_else(|e| internal_error_check(&url, e))
+                }
+            })
+            .collect::<FuturesUnordered<_>>()
+            .boxed()
+    }
+
+    fn spawn_check(&self, url: String) -> JoinHandle<SslCheck> {
+        let inner = self.inner.clone();
+        let host_limit = self.host_limit(&url);
+        let options = inner.target_options.get(&url).cloned().unwrap_or_default();
+        let timeouts = options.timeouts(inner.timeouts);
+        let retry_policy = options.retry_policy(inner.retry_policy);
+
+        task::spawn(async move {
+            let mut attempts = 0;
+            loop {
+                attempts += 1;
+                // Permits are released while waiting to retry
+                let result = {
+                    let _host_permit = host_limit.acquire().await;
+                    let _global_permit = inner.global_limit.acquire().await;
+                    let check = get_ssl_certificate(&inner, &url, &timeouts);
+                    tokio::time::timeout(timeouts.overall, check)
+                        .await
+                        .unwrap_or(Err(SslCheckError::Timeout {
+                            stage: TimeoutStage::Overall,
+                            limit: timeouts.overall,
+                            address: None,
+                        }))
+                };
+
+                match (&result, retry_policy.delay_after(attempts)) {
+                    (Err(e), Some(delay)) if e.is_retryable() => {
+                        tracing::warn!(
+                            url,
+                            attempts,
+                            error = %e,
+                            "Retrying SSL certificate check after {}",
+                            HumanDuration(delay)
+                        );
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => {
+                        if let Err(e) = &result {
+                            tracing::error!(url, attempts, error = %e, "Failed to retrieve SSL certificate");
+                        }
+                        return SslCheck {
+                            url,
+                            result,
+                            attempts,
+                            checked_at: Utc::now(),
+                        };
+                    }
+                }
+            }
+        })
+    }
+
+    // Targets on the same host and port share a per-host limit
+    fn host_limit(&self, url: &str) -> Arc<Semaphore> {
+        let mut host_limits = self
+            .inner
+            .host_limits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        host_limits
+            .entry(host_key(url))
+            .or_insert_with(|| Arc::new(Semaphore::new(self.inner.per_host_concurrency)))
+            .clone()
+    }
+}
+
+impl fmt::Debug for Checker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Checker")
+            .field("warning_threshold", &self.inner.warning_threshold)
+            .field("error_threshold", &self.inner.error_threshold)
+            .field("timeouts", &self.inner.timeouts)
+            .field("retry_policy", &self.inner.retry_policy)
+            .field("per_host_concurrency", &self.inner.per_host_concurrency)
+            .field("verifies_chain", &self.inner.chain_verifier.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+// A check whose task panicked or was cancelled is still reported, as an internal error
+fn internal_error_check(url: &str, error: task::JoinError) -> SslCheck {
+    let message = if error.is_panic() {
+        let panic = error.into_panic();
+        panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "check panicked".to_string())
+    } else {
+        error.to_string()
+    };
+    tracing::error!(url, error = %message, "Failed to properly process URL");
+
+    SslCheck {
+        url: url.to_string(),
+        result: Err(SslCheckError::InternalError(message)),
+        attempts: 1,
+        checked_at: Utc::now(),
+    }
+}
+
+fn host_key(url_str: &str) -> String {
+    match Url::parse(url_str) {
+        Ok(url) => format!(
+            "{}:{}",
+            url.host_str().unwrap_or_default(),
+            url.port_or_known_default().unwrap_or_default()
+        ),
+        Err(_) => url_str.to_string(),
+    }
+}
+
+async fn get_ssl_certificate(
+    inner: &CheckerInner,
+    url_str: &str,
+    timeouts: &Timeouts,
+) -> Result<CertCheckResult, SslCheckError> {
+    let parsed_url =
+        Url::parse(url_str).map_err(|e| SslCheckError::UrlParseError(url_str.to_string(), e))?;
+
+    tracing::debug!(url = url_str, "Attempting to retrieve SSL certificate");
+    let handshake =
+        fetch_certificates(&inner.connector, &inner.resolver, &parsed_url, timeouts).await?;
+    let certificates = handshake.certificates;
+
+    // The first certificate presented is the server's own
+    if let Some(cert_der) = certificates.first() {
+        if let Ok((_, cert)) = X509Certificate::from_der(cert_der) {
+            let untrusted = match &inner.chain_verifier {
+                Some(verifier) => verify_chain(verifier, &parsed_url, &certificates)
+                    .inspect_err(|e| {
+                        tracing::warn!(url = url_str, error = %e, "Certificate chain is not trusted")
+                    })
+                    .is_err(),
+                None => false,
+            };
+            let mut result = CertCheckResult::from_x509_certificate(
+                parsed_url,
+                inner.warning_threshold,
+                inner.error_threshold,
+                cert,
+            );
+            result.chain = certificates
+                .iter()
+                .filter_map(|der| X509Certificate::from_der(der).ok())
+                .map(|(_, cert)| ChainCertificate::from_x509_certificate(&cert))
+                .collect();
+            result.handshake_duration_ms = Some(handshake.duration.as_millis() as u64);
+            if untrusted {
+                result.is_valid = false;
+                result.findings.push(Finding::UntrustedChain);
+            }
+            return Ok(result);
+        } else {
+            tracing::warn!("No Cert Detail Found");
+        }
+    } else {
+        tracing::warn!("No TLS Info Found");
+    }
+    Err(SslCheckError::NoCertificatesFound(url_str.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Cli, Command};
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn panicking_check_becomes_internal_error() {
+        let error = task::spawn(async { panic!("certificate parser blew up") })
+            .await
+            .unwrap_err();
+
+        let check = internal_error_check("https://example.com", error);
+        assert_eq!(check.url, "https://example.com");
+        match check.result {
+            Err(SslCheckError::InternalError(message)) => {
+                assert_eq!(message, "certificate parser blew up")
+            }
+            other => panic!("Expected an internal error, got {:?}", other),
+        }
+    }
+
+    fn config_for(urls: &str) -> AppConfig {
+        let cli = Cli::parse_from(["ssl_checker", "--urls", urls, "--retries", "0"]);
+        match cli.into_command() {
+            Command::Check(args) => AppConfig::build_with_env(args, |_| None).unwrap(),
+            _ => panic!("Expected check command"),
+        }
+    }
+
+    #[tokio::test]
+    async fn check_many_streams_a_result_per_target() {
+        let config = config_for("not a url,https://127.0.0.1:1");
+        let checker = Checker::from_config(&config).unwrap();
+        let results: Vec<SslCheck> = checker.check_many(config.urls.clone()).collect().await;
+
+        let mut urls: Vec<_> = results.iter().map(|check| check.url.as_str()).collect();
+        urls.sort();
+        assert_eq!(urls, vec!["https://127.0.0.1:1", "not a url"]);
+        assert!(results.iter().all(|check| check.result.is_err()));
+    }
+
+    #[tokio::test]
+    async fn check_one_with_builder_options() {
+        let checker = Checker::builder()
+            .concurrency(0)
+            .retry_policy(RetryPolicy {
+                retries: 0,
+                ..RetryPolicy::default()
+            })
+            .build()
+            .unwrap();
+
+        // The checker is reused, and a concurrency of zero still lets checks run
+        for _ in 0..2 {
+            let check = checker.check_one("https://127.0.0.1:1").await;
+            assert_eq!(check.attempts, 1);
+            assert!(matches!(
+                check.result,
+                Err(SslCheckError::ConnectionRefused { .. })
+            ));
+        }
+    }
+}
@@ -1,23 +1,174 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde::Deserialize;
-use std::{fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
+use crate::duration::HumanDuration;
 use crate::errors::ConfigError;
+use crate::exit_code::FailOn;
+use crate::metrics::is_valid_label_name;
+use crate::output::{CsvColumn, OutputFormat, OutputOptions};
+use crate::probe::Timeouts;
+use crate::retry::RetryPolicy;
+use crate::schedule::Schedule;
+use crate::secret::{Secret, check_secret_reference, resolve_secret};
+use crate::targets::{
+    ConfigSource, DuplicateUrl, STDIN_MARKER, TargetOptions, dedup_targets, find_url_line,
+    load_url_list,
+};
 
 // Default values for the application
-const DEFAULT_ERROR_DAYS: i64 = 14;
-const DEFAULT_WARNING_DAYS: i64 = 30;
+pub const DEFAULT_ERROR_DAYS: u64 = 14;
+pub const DEFAULT_WARNING_DAYS: u64 = 30;
 const DEFAULT_LOG_LEVEL: &str = "info";
+pub const DEFAULT_CONCURRENCY: usize = 50;
+pub const DEFAULT_PER_HOST_CONCURRENCY: usize = 2;
+
+// Environment variables read by the environment configuration layer
+pub const ENV_URLS: &str = "SSL_CHECKER_URLS";
+pub const ENV_URLS_FROM: &str = "SSL_CHECKER_URLS_FROM";
+pub const ENV_ERROR_DAYS: &str = "SSL_CHECKER_ERROR_DAYS";
+pub const ENV_WARNING_DAYS: &str = "SSL_CHECKER_WARNING_DAYS";
+pub const ENV_LOG_LEVEL: &str = "SSL_CHECKER_LOG_LEVEL";
+pub const ENV_CHECK_FREQUENCY: &str = "SSL_CHECKER_CHECK_FREQUENCY";
+pub const ENV_SCHEDULE: &str = "SSL_CHECKER_SCHEDULE";
+pub const ENV_TIMEZONE: &str = "SSL_CHECKER_TIMEZONE";
+pub const ENV_RUN_ON_START: &str = "SSL_CHECKER_RUN_ON_START";
+pub const ENV_JITTER: &str = "SSL_CHECKER_JITTER";
+pub const ENV_CONNECT_TIMEOUT: &str = "SSL_CHECKER_CONNECT_TIMEOUT";
+pub const ENV_HANDSHAKE_TIMEOUT: &str = "SSL_CHECKER_HANDSHAKE_TIMEOUT";
+pub const ENV_TIMEOUT: &str = "SSL_CHECKER_TIMEOUT";
+pub const ENV_RETRIES: &str = "SSL_CHECKER_RETRIES";
+pub const ENV_RETRY_BACKOFF: &str = "SSL_CHECKER_RETRY_BACKOFF";
+pub const ENV_CONCURRENCY: &str = "SSL_CHECKER_CONCURRENCY";
+pub const ENV_PER_HOST_CONCURRENCY: &str = "SSL_CHECKER_PER_HOST_CONCURRENCY";
+pub const ENV_OUTPUT: &str = "SSL_CHECKER_OUTPUT";
+pub const ENV_CSV_COLUMNS: &str = "SSL_CHECKER_CSV_COLUMNS";
+pub const ENV_CSV_HEADER: &str = "SSL_CHECKER_CSV_HEADER";
+pub const ENV_OUT: &str = "SSL_CHECKER_OUT";
+pub const ENV_FAIL_ON: &str = "SSL_CHECKER_FAIL_ON";
+pub const ENV_MODE: &str = "SSL_CHECKER_MODE";
+pub const ENV_METRICS_ADDRESS: &str = "SSL_CHECKER_METRICS_ADDRESS";
+pub const ENV_SLACK_WEBHOOK_URL: &str = "SSL_CHECKER_SLACK_WEBHOOK_URL";
+pub const ENV_CONFIG_FILE: &str = "SSL_CHECKER_CONFIG_FILE";
 
 // --- Final application configuration structure ---
 #[derive(Debug)]
 pub struct AppConfig {
     pub urls: Vec<String>,
-    pub error_days: i64,
-    pub warning_days: i64,
+    /// Time remaining on a certificate below which it is reported as an error
+    pub error_threshold: Duration,
+    /// Time remaining on a certificate below which it is reported as a warning
+    pub warning_threshold: Duration,
     pub log_level: String,
-    pub check_frequency: Option<u32>,
-    pub slack_webhook_url: Option<String>,
+    /// Set when running in daemon mode, from `check_frequency` or `schedule`
+    pub schedule: Option<Schedule>,
+    /// Run the checks as soon as the daemon starts rather than waiting for the schedule
+    pub run_on_start: bool,
+    /// Maximum random delay added to each scheduled check, to spread the load
+    pub jitter: Option<Duration>,
+    /// Per-target settings from `[[targets]]` tables, keyed by URL
+    pub target_options: HashMap<String, TargetOptions>,
+    /// Default time limits for each check, which targets can override
+    pub timeouts: Timeouts,
+    /// Default retry behaviour for failed checks, which targets can override
+    pub retry_policy: RetryPolicy,
+    /// Maximum number of checks in progress at once
+    pub concurrency: usize,
+    /// Maximum number of checks in progress at once against the same host
+    pub per_host_concurrency: usize,
+    /// Format the results are written to stdout in, and its options
+    pub output: OutputOptions,
+    /// Least serious result that makes a single run exit non-zero
+    pub fail_on: FailOn,
+    pub mode: Mode,
+    /// Address to serve Prometheus metrics on in daemon mode
+    pub metrics_address: Option<SocketAddr>,
+    pub slack_webhook_url: Option<Secret>,
+    pub duplicate_urls: Vec<DuplicateUrl>,
+    pub sources: ConfigSources,
+}
+
+// --- How the checker reports its results ---
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Results in the configured output format, once or as a daemon
+    #[default]
+    Standard,
+    /// A single run as a Nagios/Icinga plugin: a status line with performance data
+    /// and the plugin exit codes
+    Nagios,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Standard => write!(f, "standard"),
+            Mode::Nagios => write!(f, "nagios"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+// --- Where each part of the final configuration came from ---
+#[derive(Debug, Default, Clone)]
+pub struct ConfigSources {
+    /// Config file and included fragments, in the order they were merged
+    pub files: Vec<PathBuf>,
+    /// Settings that were not left at their defaults, keyed by TOML field name
+    pub settings: HashMap<&'static str, ConfigSource>,
+    pub urls: HashMap<String, ConfigSource>,
+}
+
+// --- A configuration error and where the offending value came from, if known ---
+#[derive(Debug)]
+pub struct LocatedConfigError {
+    pub error: ConfigError,
+    pub source: Option<ConfigSource>,
+}
+
+impl From<ConfigError> for LocatedConfigError {
+    // Errors about a whole file or variable carry their own location
+    fn from(error: ConfigError) -> Self {
+        let file = |path: &PathBuf, line| ConfigSource::ConfigFile {
+            path: path.clone(),
+            line,
+        };
+        let source = match &error {
+            ConfigError::EnvVarParseError(name, _) => {
+                Some(ConfigSource::Environment { name: name.clone() })
+            }
+            ConfigError::TomlParseError(path, err) => Some(file(path, toml_error_line(path, err))),
+            ConfigError::FileReadError(path, _)
+            | ConfigError::FileNotFound(path)
+            | ConfigError::NestedInclude(path) => Some(file(path, None)),
+            _ => None,
+        };
+        Self { error, source }
+    }
+}
+
+// --- How secret references are handled while building the configuration ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretHandling {
+    /// Read the referenced variables and files, and run commands
+    Resolve,
+    /// Only check their syntax, so validating a config never reads secrets or runs commands
+    CheckSyntax,
 }
 
 // --- Configuration structure for TOML file ---
@@ -25,89 +176,1069 @@ pub struct AppConfig {
 #[serde(deny_unknown_fields)]
 pub struct TomlConfig {
     urls: Option<Vec<String>>,
-    error_days: Option<i64>,
-    warning_days: Option<i64>,
+    urls_from: Option<PathBuf>,
+    include: Option<Vec<String>>,
+    error_days: Option<HumanDuration>,
+    warning_days: Option<HumanDuration>,
+    log_level: Option<String>,
+    check_frequency: Option<HumanDuration>,
+    schedule: Option<String>,
+    timezone: Option<String>,
+    run_on_start: Option<bool>,
+    jitter: Option<HumanDuration>,
+    connect_timeout: Option<HumanDuration>,
+    handshake_timeout: Option<HumanDuration>,
+    timeout: Option<HumanDuration>,
+    retries: Option<u32>,
+    retry_backoff: Option<HumanDuration>,
+    concurrency: Option<usize>,
+    per_host_concurrency: Option<usize>,
+    output: Option<OutputFormat>,
+    csv_columns: Option<Vec<CsvColumn>>,
+    csv_header: Option<bool>,
+    out: Option<PathBuf>,
+    fail_on: Option<FailOn>,
+    mode: Option<Mode>,
+    metrics_address: Option<SocketAddr>,
+    slack_webhook_url: Option<String>,
+    targets: Option<Vec<TomlTarget>>,
+
+    // Options from `targets` tables, filled in by take_targets
+    #[serde(skip)]
+    target_options: HashMap<String, TargetOptions>,
+}
+
+// --- A `[[targets]]` table in the TOML file ---
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct TomlTarget {
+    url: String,
+    interval: Option<HumanDuration>,
+    jitter: Option<HumanDuration>,
+    connect_timeout: Option<HumanDuration>,
+    handshake_timeout: Option<HumanDuration>,
+    timeout: Option<HumanDuration>,
+    retries: Option<u32>,
+    tags: Option<BTreeMap<String, String>>,
+}
+
+// --- Configuration structure for environment variables ---
+#[derive(Debug, Default)]
+struct EnvConfig {
+    urls: Option<Vec<String>>,
+    urls_from: Option<PathBuf>,
+    error_days: Option<HumanDuration>,
+    warning_days: Option<HumanDuration>,
     log_level: Option<String>,
-    check_frequency: Option<u32>,
+    check_frequency: Option<HumanDuration>,
+    schedule: Option<String>,
+    timezone: Option<String>,
+    run_on_start: Option<bool>,
+    jitter: Option<HumanDuration>,
+    connect_timeout: Option<HumanDuration>,
+    handshake_timeout: Option<HumanDuration>,
+    timeout: Option<HumanDuration>,
+    retries: Option<u32>,
+    retry_backoff: Option<HumanDuration>,
+    concurrency: Option<usize>,
+    per_host_concurrency: Option<usize>,
+    output: Option<OutputFormat>,
+    csv_columns: Option<Vec<CsvColumn>>,
+    csv_header: Option<bool>,
+    out: Option<PathBuf>,
+    fail_on: Option<FailOn>,
+    mode: Option<Mode>,
+    metrics_address: Option<SocketAddr>,
     slack_webhook_url: Option<String>,
+    config_file: Option<PathBuf>,
+}
+
+impl EnvConfig {
+    // Returns the settings that could be read, along with an error for each variable
+    // that couldn't be parsed
+    fn from_lookup(env: &dyn Fn(&str) -> Option<String>) -> (Self, Vec<ConfigError>) {
+        let mut parser = EnvParser {
+            env,
+            errors: Vec::new(),
+        };
+
+        let config = EnvConfig {
+            urls: parser.var(ENV_URLS).map(|value| {
+                value
+                    .split(',')
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                    .collect()
+            }),
+            urls_from: parser.var(ENV_URLS_FROM).map(PathBuf::from),
+            error_days: parser.parse(ENV_ERROR_DAYS),
+            warning_days: parser.parse(ENV_WARNING_DAYS),
+            log_level: parser.var(ENV_LOG_LEVEL),
+            check_frequency: parser.parse(ENV_CHECK_FREQUENCY),
+            schedule: parser.var(ENV_SCHEDULE),
+            timezone: parser.var(ENV_TIMEZONE),
+            run_on_start: parser.parse(ENV_RUN_ON_START),
+            jitter: parser.parse(ENV_JITTER),
+            connect_timeout: parser.parse(ENV_CONNECT_TIMEOUT),
+            handshake_timeout: parser.parse(ENV_HANDSHAKE_TIMEOUT),
+            timeout: parser.parse(ENV_TIMEOUT),
+            retries: parser.parse(ENV_RETRIES),
+            retry_backoff: parser.parse(ENV_RETRY_BACKOFF),
+            concurrency: parser.parse(ENV_CONCURRENCY),
+            per_host_concurrency: parser.parse(ENV_PER_HOST_CONCURRENCY),
+            output: parser.parse(ENV_OUTPUT),
+            csv_columns: parser.parse_list(ENV_CSV_COLUMNS),
+            csv_header: parser.parse(ENV_CSV_HEADER),
+            out: parser.var(ENV_OUT).map(PathBuf::from),
+            fail_on: parser.parse(ENV_FAIL_ON),
+            mode: parser.parse(ENV_MODE),
+            metrics_address: parser.parse(ENV_METRICS_ADDRESS),
+            slack_webhook_url: parser.var(ENV_SLACK_WEBHOOK_URL),
+            config_file: parser.var(ENV_CONFIG_FILE).map(PathBuf::from),
+        };
+        (config, parser.errors)
+    }
+
+    // Removes the URL settings from the config, returning them as targets with their source
+    fn take_targets(&mut self) -> Result<Vec<(String, ConfigSource)>, ConfigError> {
+        let mut targets: Vec<(String, ConfigSource)> = self
+            .urls
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|url| {
+                let source = ConfigSource::Environment {
+                    name: ENV_URLS.to_string(),
+                };
+                (url, source)
+            })
+            .collect();
+
+        if let Some(urls_from) = self.urls_from.take() {
+            targets.extend(load_url_list(&urls_from)?);
+        }
+
+        Ok(targets)
+    }
+}
+
+// Reads environment variables, collecting every invalid value rather than stopping at
+// the first
+struct EnvParser<'a> {
+    env: &'a dyn Fn(&str) -> Option<String>,
+    errors: Vec<ConfigError>,
+}
+
+impl EnvParser<'_> {
+    // Empty variables are treated as unset
+    fn var(&self, name: &str) -> Option<String> {
+        (self.env)(name).filter(|value| !value.trim().is_empty())
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        let value = self.var(name)?;
+        parse_env_value(name, &value)
+            .map_err(|error| self.errors.push(error))
+            .ok()
+    }
+
+    // A comma-separated list, in which every item must parse
+    fn parse_list<T: FromStr>(&mut self, name: &str) -> Option<Vec<T>> {
+        let value = self.var(name)?;
+        value
+            .split(',')
+            .map(|item| parse_env_value(name, item.trim()))
+            .collect::<Result<_, _>>()
+            .map_err(|error| self.errors.push(error))
+            .ok()
+    }
+}
+
+fn parse_env_value<T: FromStr>(name: &str, value: &str) -> Result<T, ConfigError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ConfigError::EnvVarParseError(name.to_string(), value.to_string()))
+}
+
+impl TomlConfig {
+    // Removes the URL settings from the config, returning them as targets with their source
+    fn take_targets(
+        &mut self,
+        path: &Path,
+        content: &str,
+    ) -> Result<Vec<(String, ConfigSource)>, ConfigError> {
+        // URLs appear in the file in the order they're taken, so a repeated URL is
+        // located by skipping the occurrences already found
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut locate = |url: &str| {
+            let occurrence = occurrences.entry(url.to_string()).or_default();
+            let source = ConfigSource::ConfigFile {
+                path: path.to_path_buf(),
+                line: find_url_line(content, url, *occurrence),
+            };
+            *occurrence += 1;
+            source
+        };
+
+        let mut targets: Vec<(String, ConfigSource)> = self
+            .urls
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|url| {
+                let source = locate(&url);
+                (url, source)
+            })
+            .collect();
+
+        for target in self.targets.take().unwrap_or_default() {
+            let source = locate(&target.url);
+            let options = TargetOptions {
+                interval: target.interval.map(Duration::from),
+                jitter: target.jitter.map(Duration::from),
+                connect_timeout: target.connect_timeout.map(Duration::from),
+                handshake_timeout: target.handshake_timeout.map(Duration::from),
+                timeout: target.timeout.map(Duration::from),
+                retries: target.retries,
+                tags: target.tags.unwrap_or_default(),
+            };
+            self.target_options.insert(target.url.clone(), options);
+            targets.push((target.url, source));
+        }
+
+        if let Some(urls_from) = self.urls_from.take() {
+            targets.extend(load_url_list(&resolve_relative(path, &urls_from))?);
+        }
+
+        Ok(targets)
+    }
+
+    // Values already set take precedence over those from the fragment
+    fn merge_fragment(&mut self, fragment: TomlConfig) {
+        self.error_days = self.error_days.or(fragment.error_days);
+        self.warning_days = self.warning_days.or(fragment.warning_days);
+        self.log_level = self.log_level.take().or(fragment.log_level);
+        self.check_frequency = self.check_frequency.or(fragment.check_frequency);
+        self.schedule = self.schedule.take().or(fragment.schedule);
+        self.timezone = self.timezone.take().or(fragment.timezone);
+        self.run_on_start = self.run_on_start.or(fragment.run_on_start);
+        self.jitter = self.jitter.or(fragment.jitter);
+        for (url, options) in fragment.target_options {
+            self.target_options.entry(url).or_insert(options);
+        }
+        self.connect_timeout = self.connect_timeout.or(fragment.connect_timeout);
+        self.handshake_timeout = self.handshake_timeout.or(fragment.handshake_timeout);
+        self.timeout = self.timeout.or(fragment.timeout);
+        self.retries = self.retries.or(fragment.retries);
+        self.retry_backoff = self.retry_backoff.or(fragment.retry_backoff);
+        self.concurrency = self.concurrency.or(fragment.concurrency);
+        self.per_host_concurrency = self.per_host_concurrency.or(fragment.per_host_concurrency);
+        self.output = self.output.or(fragment.output);
+        self.csv_columns = self.csv_columns.take().or(fragment.csv_columns);
+        self.csv_header = self.csv_header.or(fragment.csv_header);
+        self.out = self.out.take().or(fragment.out);
+        self.fail_on = self.fail_on.or(fragment.fail_on);
+        self.mode = self.mode.or(fragment.mode);
+        self.metrics_address = self.metrics_address.or(fragment.metrics_address);
+        self.slack_webhook_url = self.slack_webhook_url.take().or(fragment.slack_webhook_url);
+    }
+}
+
+// Resolves a path referenced from a config file relative to that file's directory
+fn resolve_relative(config_path: &Path, referenced: &Path) -> PathBuf {
+    if referenced == Path::new(STDIN_MARKER) {
+        return referenced.to_path_buf();
+    }
+    match config_path.parent() {
+        Some(dir) => dir.join(referenced),
+        None => referenced.to_path_buf(),
+    }
+}
+
+// The 1-based line a TOML parse error points at. The error only records a byte offset,
+// so the file is read again to count lines.
+fn toml_error_line(path: &Path, error: &toml::de::Error) -> Option<usize> {
+    let offset = error.span()?.start;
+    let content = fs::read_to_string(path).ok()?;
+    Some(content.get(..offset)?.matches('\n').count() + 1)
+}
+
+fn read_toml_file(path: &Path) -> Result<(TomlConfig, String), ConfigError> {
+    if !path.exists() {
+        return Err(ConfigError::FileNotFound(path.to_path_buf()));
+    }
+    let toml_content = fs::read_to_string(path)
+        .map_err(|e| ConfigError::FileReadError(path.to_path_buf(), Box::new(e)))?;
+    let toml_config = toml::from_str(&toml_content)
+        .map_err(|e| ConfigError::TomlParseError(path.to_path_buf(), Box::new(e)))?;
+    Ok((toml_config, toml_content))
+}
+
+fn expand_include(config_path: &Path, pattern: &str) -> Result<Vec<PathBuf>, ConfigError> {
+    let full_pattern = resolve_relative(config_path, Path::new(pattern));
+    let paths = glob::glob(&full_pattern.to_string_lossy())
+        .map_err(|e| ConfigError::IncludePatternError(pattern.to_string(), Box::new(e)))?;
+
+    paths
+        .map(|entry| {
+            entry.map_err(|e| {
+                let path = e.path().to_path_buf();
+                ConfigError::FileReadError(path, Box::new(e.into()))
+            })
+        })
+        .collect()
+}
+
+// Config files that were loaded, along with their content
+type LoadedFiles = Vec<(PathBuf, String)>;
+type LoadedConfig = (TomlConfig, Vec<(String, ConfigSource)>, LoadedFiles);
+
+// Loads a config file and merges in any included fragments
+fn load_config_file(path: &Path) -> Result<LoadedConfig, ConfigError> {
+    let (mut toml_config, content) = read_toml_file(path)?;
+    let mut targets = toml_config.take_targets(path, &content)?;
+    let mut loaded_files = vec![(path.to_path_buf(), content)];
+
+    for pattern in toml_config.include.take().unwrap_or_default() {
+        for fragment_path in expand_include(path, &pattern)? {
+            let (mut fragment, fragment_content) = read_toml_file(&fragment_path)?;
+            if fragment.include.is_some() {
+                return Err(ConfigError::NestedInclude(fragment_path));
+            }
+            targets.extend(fragment.take_targets(&fragment_path, &fragment_content)?);
+            toml_config.merge_fragment(fragment);
+            loaded_files.push((fragment_path, fragment_content));
+        }
+    }
+
+    Ok((toml_config, targets, loaded_files))
+}
+
+/// Finds the 1-based line on which a top-level TOML `key` is assigned.
+pub fn find_key_line(content: &str, key: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| {
+            line.trim_start()
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        })
+        .map(|idx| idx + 1)
+}
+
+// Picks each setting from the highest precedence layer, recording where it came from
+struct SettingResolver<'a> {
+    loaded_files: &'a [(PathBuf, String)],
+    settings: HashMap<&'static str, ConfigSource>,
+}
+
+impl SettingResolver<'_> {
+    fn pick<T>(
+        &mut self,
+        key: &'static str,
+        env_name: &str,
+        cli: Option<T>,
+        env: Option<T>,
+        toml: Option<T>,
+    ) -> Option<T> {
+        let (value, source) = if cli.is_some() {
+            (cli, ConfigSource::CommandLine)
+        } else if env.is_some() {
+            let source = ConfigSource::Environment {
+                name: env_name.to_string(),
+            };
+            (env, source)
+        } else if toml.is_some() {
+            (toml, self.toml_source(key))
+        } else {
+            return None;
+        };
+        self.settings.insert(key, source);
+        value
+    }
+
+    // Attaches the source of the setting an error is about
+    fn locate(&self, key: &str, error: ConfigError) -> LocatedConfigError {
+        LocatedConfigError {
+            error,
+            source: self.settings.get(key).cloned(),
+        }
+    }
+
+    // Orders two settings by the precedence of the layers they were taken from
+    fn compare_layers(&self, a: &str, b: &str) -> std::cmp::Ordering {
+        let rank = |key: &str| match self.settings.get(key) {
+            Some(ConfigSource::CommandLine) => 0,
+            Some(ConfigSource::Environment { .. }) => 1,
+            _ => 2,
+        };
+        rank(a).cmp(&rank(b))
+    }
+
+    // The first file to set a key wins, matching how fragments are merged
+    fn toml_source(&self, key: &str) -> ConfigSource {
+        self.loaded_files
+            .iter()
+            .find_map(|(path, content)| {
+                find_key_line(content, key).map(|line| ConfigSource::ConfigFile {
+                    path: path.clone(),
+                    line: Some(line),
+                })
+            })
+            .unwrap_or_else(|| ConfigSource::ConfigFile {
+                path: self.loaded_files[0].0.clone(),
+                line: None,
+            })
+    }
 }
 
 impl AppConfig {
+    /// Builds the configuration from CLI arguments, `SSL_CHECKER_*` environment
+    /// variables and the TOML config file, in that order of precedence.
     pub fn build(args: CliArgs) -> Result<Self, ConfigError> {
+        Self::build_with_env(args, |name| std::env::var(name).ok())
+    }
+
+    /// Same as [`AppConfig::build`], reading environment variables through `env`.
+    pub fn build_with_env(
+        args: CliArgs,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        // The first error is reported, as nothing can run until it's fixed
+        Self::build_collecting(args, &env, SecretHandling::Resolve).map_err(|errors| {
+            errors
+                .into_iter()
+                .next()
+                .expect("errors are never empty")
+                .error
+        })
+    }
+
+    /// Same as [`AppConfig::build`], but returns every error found along with where it
+    /// came from rather than stopping at the first.
+    pub fn build_reporting_all(
+        args: CliArgs,
+        secrets: SecretHandling,
+    ) -> Result<Self, Vec<LocatedConfigError>> {
+        let env = |name: &str| std::env::var(name).ok();
+        Self::build_collecting(args, &env, secrets)
+    }
+
+    // Carries on past errors where a setting can be left at its default, so they can
+    // all be reported at once. Errors loading the config file stop the build.
+    fn build_collecting(
+        args: CliArgs,
+        env: &dyn Fn(&str) -> Option<String>,
+        secrets: SecretHandling,
+    ) -> Result<Self, Vec<LocatedConfigError>> {
+        let (mut env_config, env_errors) = EnvConfig::from_lookup(env);
+        let mut errors: Vec<LocatedConfigError> = env_errors.into_iter().map(Into::into).collect();
         let mut toml_config = TomlConfig::default();
+        let mut toml_targets = Vec::new();
+        let mut loaded_files = Vec::new();
 
-        if let Some(path_to_load) = &args.config_file {
-            if !path_to_load.exists() {
-                return Err(ConfigError::FileNotFound(path_to_load.clone()));
+        if let Some(path_to_load) = args
+            .config_file
+            .as_ref()
+            .or(env_config.config_file.as_ref())
+        {
+            match load_config_file(path_to_load) {
+                Ok(loaded) => (toml_config, toml_targets, loaded_files) = loaded,
+                Err(error) => {
+                    errors.push(error.into());
+                    return Err(errors);
+                }
             }
-            let toml_content = fs::read_to_string(path_to_load)
-                .map_err(|e| ConfigError::FileReadError(path_to_load.clone(), Box::new(e)))?;
-            toml_config = toml::from_str(&toml_content)
-                .map_err(|e| ConfigError::TomlParseError(path_to_load.clone(), Box::new(e)))?;
         }
         // If effective_config_path was None, toml_config remains TomlConfig::default()
 
-        let urls = args.urls.or(toml_config.urls).filter(|v| !v.is_empty());
-        if urls.is_none() {
-            return Err(ConfigError::MissingUrls);
+        // Targets from a higher precedence layer replace those from lower layers
+        let mut cli_targets: Vec<(String, ConfigSource)> = args
+            .urls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|url| (url, ConfigSource::CommandLine))
+            .collect();
+        let errors_before_targets = errors.len();
+        if let Some(urls_from) = &args.urls_from {
+            match load_url_list(urls_from) {
+                Ok(targets) => cli_targets.extend(targets),
+                Err(error) => errors.push(error.into()),
+            }
+        }
+
+        let targets = if !cli_targets.is_empty() {
+            cli_targets
+        } else {
+            let env_targets = env_config.take_targets().unwrap_or_else(|error| {
+                errors.push(error.into());
+                Vec::new()
+            });
+            if !env_targets.is_empty() {
+                env_targets
+            } else {
+                toml_targets
+            }
+        };
+        // A URL list that couldn't be read has already been reported
+        if targets.is_empty() && errors.len() == errors_before_targets {
+            errors.push(ConfigError::MissingUrls.into());
+        }
+        let (targets, duplicate_urls) = dedup_targets(targets);
+
+        let mut resolver = SettingResolver {
+            loaded_files: &loaded_files,
+            settings: HashMap::new(),
+        };
+        let error_threshold = resolver
+            .pick(
+                "error_days",
+                ENV_ERROR_DAYS,
+                args.error_days,
+                env_config.error_days,
+                toml_config.error_days,
+            )
+            .unwrap_or(HumanDuration::from_days(DEFAULT_ERROR_DAYS));
+        let warning_threshold = resolver
+            .pick(
+                "warning_days",
+                ENV_WARNING_DAYS,
+                args.warning_days,
+                env_config.warning_days,
+                toml_config.warning_days,
+            )
+            .unwrap_or(HumanDuration::from_days(DEFAULT_WARNING_DAYS));
+        let log_level = resolver
+            .pick(
+                "log_level",
+                ENV_LOG_LEVEL,
+                args.log_level,
+                env_config.log_level,
+                toml_config.log_level,
+            )
+            .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string());
+        let check_frequency = resolver.pick(
+            "check_frequency",
+            ENV_CHECK_FREQUENCY,
+            args.check_frequency,
+            env_config.check_frequency,
+            toml_config.check_frequency,
+        );
+        let cron_expression = resolver.pick(
+            "schedule",
+            ENV_SCHEDULE,
+            args.schedule,
+            env_config.schedule,
+            toml_config.schedule,
+        );
+        let timezone = resolver
+            .pick(
+                "timezone",
+                ENV_TIMEZONE,
+                args.timezone,
+                env_config.timezone,
+                toml_config.timezone,
+            )
+            .and_then(|name| match name.parse::<chrono_tz::Tz>() {
+                Ok(timezone) => Some(timezone),
+                Err(_) => {
+                    let error =
+                        ConfigError::InvalidSchedule(format!("unknown timezone '{}'", name));
+                    errors.push(resolver.locate("timezone", error));
+                    None
+                }
+            })
+            .unwrap_or(chrono_tz::UTC);
+        let cron = |expression: &str| {
+            Schedule::cron(expression, timezone)
+                .map(Some)
+                .map_err(ConfigError::InvalidSchedule)
+        };
+        let schedule = match (check_frequency, cron_expression) {
+            (Some(check_frequency), Some(cron_expression)) => {
+                // A setting from a higher precedence layer overrides the other one
+                match resolver.compare_layers("check_frequency", "schedule") {
+                    std::cmp::Ordering::Less => {
+                        Ok(Some(Schedule::Interval(check_frequency.into())))
+                    }
+                    std::cmp::Ordering::Greater => cron(&cron_expression),
+                    std::cmp::Ordering::Equal => Err(ConfigError::InvalidSchedule(
+                        "check_frequency and schedule cannot both be set".to_string(),
+                    )),
+                }
+            }
+            (Some(check_frequency), None) => Ok(Some(Schedule::Interval(check_frequency.into()))),
+            (None, Some(cron_expression)) => cron(&cron_expression),
+            (None, None) => Ok(None),
+        };
+        let schedule = schedule.unwrap_or_else(|error| {
+            errors.push(resolver.locate("schedule", error));
+            None
+        });
+        // Interval schedules have always run straight away
+        let run_on_start = resolver
+            .pick(
+                "run_on_start",
+                ENV_RUN_ON_START,
+                match (args.run_on_start, args.no_run_on_start) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                },
+                env_config.run_on_start,
+                toml_config.run_on_start,
+            )
+            .unwrap_or(matches!(schedule, Some(Schedule::Interval(_))));
+        let jitter = resolver.pick(
+            "jitter",
+            ENV_JITTER,
+            args.jitter,
+            env_config.jitter,
+            toml_config.jitter,
+        );
+        let default_timeouts = Timeouts::default();
+        let connect_timeout = resolver
+            .pick(
+                "connect_timeout",
+                ENV_CONNECT_TIMEOUT,
+                args.connect_timeout,
+                env_config.connect_timeout,
+                toml_config.connect_timeout,
+            )
+            .map_or(default_timeouts.connect, Duration::from);
+        let handshake_timeout = resolver
+            .pick(
+                "handshake_timeout",
+                ENV_HANDSHAKE_TIMEOUT,
+                args.handshake_timeout,
+                env_config.handshake_timeout,
+                toml_config.handshake_timeout,
+            )
+            .map_or(default_timeouts.handshake, Duration::from);
+        let timeout = resolver
+            .pick(
+                "timeout",
+                ENV_TIMEOUT,
+                args.timeout,
+                env_config.timeout,
+                toml_config.timeout,
+            )
+            .map_or(default_timeouts.overall, Duration::from);
+        let default_retry_policy = RetryPolicy::default();
+        let retries = resolver
+            .pick(
+                "retries",
+                ENV_RETRIES,
+                args.retries,
+                env_config.retries,
+                toml_config.retries,
+            )
+            .unwrap_or(default_retry_policy.retries);
+        let retry_backoff = resolver
+            .pick(
+                "retry_backoff",
+                ENV_RETRY_BACKOFF,
+                args.retry_backoff,
+                env_config.retry_backoff,
+                toml_config.retry_backoff,
+            )
+            .map_or(default_retry_policy.backoff, Duration::from);
+        let concurrency = resolver
+            .pick(
+                "concurrency",
+                ENV_CONCURRENCY,
+                args.concurrency,
+                env_config.concurrency,
+                toml_config.concurrency,
+            )
+            .unwrap_or(DEFAULT_CONCURRENCY);
+        let per_host_concurrency = resolver
+            .pick(
+                "per_host_concurrency",
+                ENV_PER_HOST_CONCURRENCY,
+                args.per_host_concurrency,
+                env_config.per_host_concurrency,
+                toml_config.per_host_concurrency,
+            )
+            .unwrap_or(DEFAULT_PER_HOST_CONCURRENCY);
+        // A limit of zero would leave every check waiting for a permit forever
+        for (setting, limit) in [
+            ("concurrency", concurrency),
+            ("per_host_concurrency", per_host_concurrency),
+        ] {
+            if limit == 0 {
+                let error = ConfigError::InvalidValue(
+                    setting.to_string(),
+                    "must be at least 1".to_string(),
+                );
+                errors.push(resolver.locate(setting, error));
+            }
+        }
+        // A zero interval would check every target again as soon as it finished
+        if let Some(Schedule::Interval(interval)) = &schedule
+            && interval.is_zero()
+        {
+            let error = ConfigError::InvalidValue(
+                "check_frequency".to_string(),
+                "must be greater than zero".to_string(),
+            );
+            errors.push(resolver.locate("check_frequency", error));
+        }
+        let default_output = OutputOptions::default();
+        let output_format = resolver
+            .pick(
+                "output",
+                ENV_OUTPUT,
+                args.output,
+                env_config.output,
+                toml_config.output,
+            )
+            .unwrap_or_default();
+        let csv_columns = resolver
+            .pick(
+                "csv_columns",
+                ENV_CSV_COLUMNS,
+                args.csv_columns,
+                env_config.csv_columns,
+                toml_config.csv_columns,
+            )
+            .unwrap_or(default_output.csv_columns);
+        let csv_header = resolver
+            .pick(
+                "csv_header",
+                ENV_CSV_HEADER,
+                args.no_csv_header.then_some(false),
+                env_config.csv_header,
+                toml_config.csv_header,
+            )
+            .unwrap_or(default_output.csv_header);
+        let out = resolver.pick("out", ENV_OUT, args.out, env_config.out, toml_config.out);
+        let fail_on = resolver
+            .pick(
+                "fail_on",
+                ENV_FAIL_ON,
+                args.fail_on,
+                env_config.fail_on,
+                toml_config.fail_on,
+            )
+            .unwrap_or_default();
+        let mode = resolver
+            .pick(
+                "mode",
+                ENV_MODE,
+                args.mode,
+                env_config.mode,
+                toml_config.mode,
+            )
+            .unwrap_or_default();
+        let metrics_address = resolver.pick(
+            "metrics_address",
+            ENV_METRICS_ADDRESS,
+            args.metrics_address,
+            env_config.metrics_address,
+            toml_config.metrics_address,
+        );
+        let slack_webhook_url = resolver
+            .pick(
+                "slack_webhook_url",
+                ENV_SLACK_WEBHOOK_URL,
+                args.slack_webhook_url,
+                env_config.slack_webhook_url,
+                toml_config.slack_webhook_url,
+            )
+            .and_then(|value| {
+                let secret = match secrets {
+                    SecretHandling::Resolve => resolve_secret("slack_webhook_url", &value, env),
+                    SecretHandling::CheckSyntax => {
+                        check_secret_reference("slack_webhook_url", &value)
+                    }
+                };
+                secret
+                    .map_err(|error| errors.push(resolver.locate("slack_webhook_url", error)))
+                    .ok()
+            });
+
+        // Options only apply to targets that made it into the final list
+        let mut target_options = toml_config.target_options;
+        target_options.retain(|url, _| targets.iter().any(|(target, _)| target == url));
+        // Tags become metric labels as they are, so names that aren't valid label names
+        // or that clash with the target label are rejected
+        for (url, source) in &targets {
+            if target_options
+                .get(url)
+                .and_then(|options| options.interval)
+                .is_some_and(|interval| interval.is_zero())
+            {
+                errors.push(LocatedConfigError {
+                    error: ConfigError::InvalidValue(
+                        format!("interval of {}", url),
+                        "must be greater than zero".to_string(),
+                    ),
+                    source: Some(source.clone()),
+                });
+            }
+            let tags = target_options.get(url).map(|options| &options.tags);
+            for name in tags
+                .into_iter()
+                .flat_map(|tags| tags.keys())
+                .filter(|name| !is_valid_label_name(name) || name.as_str() == "target")
+            {
+                errors.push(LocatedConfigError {
+                    error: ConfigError::InvalidTag(url.clone(), name.clone()),
+                    source: Some(source.clone()),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
         }
 
+        let sources = ConfigSources {
+            files: loaded_files.iter().map(|(path, _)| path.clone()).collect(),
+            settings: resolver.settings,
+            urls: targets.iter().cloned().collect(),
+        };
+
         Ok(AppConfig {
-            urls: urls.unwrap(), // Safe due to the check above
-            error_days: args
-                .error_days
-                .or(toml_config.error_days)
-                .unwrap_or(DEFAULT_ERROR_DAYS),
-            warning_days: args
-                .warning_days
-                .or(toml_config.warning_days)
-                .unwrap_or(DEFAULT_WARNING_DAYS),
-            log_level: args
-                .log_level
-                .or(toml_config.log_level)
-                .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string()),
-            check_frequency: args.check_frequency.or(toml_config.check_frequency),
-            slack_webhook_url: args.slack_webhook_url.or(toml_config.slack_webhook_url),
+            urls: targets.into_iter().map(|(url, _)| url).collect(),
+            error_threshold: error_threshold.into(),
+            warning_threshold: warning_threshold.into(),
+            log_level,
+            schedule,
+            run_on_start,
+            jitter: jitter.map(Duration::from),
+            target_options,
+            timeouts: Timeouts {
+                connect: connect_timeout,
+                handshake: handshake_timeout,
+                overall: timeout,
+            },
+            retry_policy: RetryPolicy {
+                retries,
+                backoff: retry_backoff,
+            },
+            concurrency,
+            per_host_concurrency,
+            output: OutputOptions {
+                format: output_format,
+                csv_columns,
+                csv_header,
+                path: out,
+            },
+            fail_on,
+            mode,
+            metrics_address,
+            slack_webhook_url,
+            duplicate_urls,
+            sources,
         })
     }
 }
 
-// --- CLI arguments structure using clap ---
+impl AppConfig {
+    /// The schedule a target is checked on in daemon mode: its own interval if it has
+    /// one, otherwise the global schedule.
+    pub fn schedule_for(&self, url: &str) -> Option<Schedule> {
+        let schedule = self.schedule.as_ref()?;
+        match self.target_options.get(url).and_then(|o| o.interval) {
+            Some(interval) => Some(Schedule::Interval(interval)),
+            None => Some(schedule.clone()),
+        }
+    }
+
+    /// The time limits for checking a target, with any of its own overrides applied.
+    pub fn timeouts_for(&self, url: &str) -> Timeouts {
+        match self.target_options.get(url) {
+            Some(options) => options.timeouts(self.timeouts),
+            None => self.timeouts,
+        }
+    }
+
+    /// The retry behaviour for a target, with its own retry count applied.
+    pub fn retry_policy_for(&self, url: &str) -> RetryPolicy {
+        match self.target_options.get(url) {
+            Some(options) => options.retry_policy(self.retry_policy),
+            None => self.retry_policy,
+        }
+    }
+
+    pub fn jitter_for(&self, url: &str) -> Duration {
+        self.target_options
+            .get(url)
+            .and_then(|options| options.jitter)
+            .or(self.jitter)
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+// --- CLI structure using clap ---
 #[derive(Parser, Debug)]
 #[clap(author, version, about = "SSL Certificate Checker Utility", long_about = None)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    // Arguments for the default `check` command when no subcommand is given
+    #[clap(flatten)]
+    args: CliArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check SSL certificates (the default when no subcommand is given)
+    Check(CliArgs),
+    /// Validate the configuration and exit without running any checks
+    ValidateConfig(CliArgs),
+}
+
+impl Cli {
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Check(self.args))
+    }
+}
+
+// --- CLI arguments structure shared by the subcommands ---
+#[derive(Args, Debug, Clone)]
 pub struct CliArgs {
     /// List of URLs to check SSL certificates for (comma-separated or multiple flags)
     #[clap(short, long, value_delimiter = ',', num_args = 1..)]
     urls: Option<Vec<String>>,
 
-    /// Days remaining on SSL certificate to trigger an error
-    #[clap(short = 'e', long, value_name = "DAYS")]
-    error_days: Option<i64>,
+    /// Newline-delimited file of URLs to check ("-" reads from stdin)
+    #[clap(long, value_name = "FILE_PATH")]
+    urls_from: Option<PathBuf>,
+
+    /// Time remaining on SSL certificate to trigger an error, in days or as a duration (e.g. 36h)
+    #[clap(short = 'e', long, value_name = "DURATION")]
+    error_days: Option<HumanDuration>,
 
-    /// Days remaining on SSL certificate to trigger a warning
-    #[clap(short = 'w', long, value_name = "DAYS")]
-    warning_days: Option<i64>,
+    /// Time remaining on SSL certificate to trigger a warning, in days or as a duration (e.g. 6w)
+    #[clap(short = 'w', long, value_name = "DURATION")]
+    warning_days: Option<HumanDuration>,
 
     /// Logging level (e.g., error, warn, info, debug, trace)
     #[clap(short, long, value_name = "LEVEL")]
     log_level: Option<String>,
 
-    /// Slack webhook URL for notifications
+    /// Slack webhook URL for notifications (or an env:NAME, file:PATH or cmd:COMMAND reference)
     #[clap(long, value_name = "URL")]
     slack_webhook_url: Option<String>,
 
-    /// Frequency to check urls in days - activating this runing in daemon mode
+    /// Frequency to check urls, in days or as a duration (e.g. 15m) - activating this runing in daemon mode
     #[clap(long, value_name = "FREQUENCY")]
-    check_frequency: Option<u32>,
+    check_frequency: Option<HumanDuration>,
+
+    /// Cron expression to run the checks on (e.g. "0 8 * * MON-FRI") - activating this runs in daemon mode
+    #[clap(long, value_name = "CRON")]
+    schedule: Option<String>,
+
+    /// Time zone the cron schedule is evaluated in (e.g. Europe/London), defaults to UTC
+    #[clap(long, value_name = "TIMEZONE")]
+    timezone: Option<String>,
+
+    /// Run the checks immediately when daemon mode starts, as well as on the schedule
+    #[clap(long)]
+    run_on_start: bool,
+
+    /// Wait for the schedule before running the checks, even with --check-frequency
+    #[clap(long, conflicts_with = "run_on_start")]
+    no_run_on_start: bool,
+
+    /// Maximum random delay added to each scheduled check to spread the load (e.g. 5m)
+    #[clap(long, value_name = "DURATION")]
+    jitter: Option<HumanDuration>,
+
+    /// Maximum time to wait for each TCP connection to open (e.g. 5s), defaults to 10s
+    #[clap(long, value_name = "DURATION")]
+    connect_timeout: Option<HumanDuration>,
+
+    /// Maximum time to wait for each TLS handshake to complete (e.g. 5s), defaults to 10s
+    #[clap(long, value_name = "DURATION")]
+    handshake_timeout: Option<HumanDuration>,
+
+    /// Maximum time to wait for each URL's certificate, in days or as a duration (e.g. 30s), defaults to 30s
+    #[clap(long, value_name = "DURATION")]
+    timeout: Option<HumanDuration>,
+
+    /// Number of times to retry a check that failed with a transient error, defaults to 2
+    #[clap(long, value_name = "COUNT")]
+    retries: Option<u32>,
+
+    /// Delay before the first retry, doubling for each further retry (e.g. 5s), defaults to 1s
+    #[clap(long, value_name = "DURATION")]
+    retry_backoff: Option<HumanDuration>,
+
+    /// Maximum number of checks to run at once, defaults to 50
+    #[clap(long, value_name = "COUNT")]
+    concurrency: Option<usize>,
+
+    /// Maximum number of checks to run at once against the same host, defaults to 2
+    #[clap(long, value_name = "COUNT")]
+    per_host_concurrency: Option<usize>,
+
+    /// Format to write the results in, defaults to text
+    #[clap(short, long, value_enum, value_name = "FORMAT")]
+    output: Option<OutputFormat>,
+
+    /// Columns to include in CSV output, in order (comma-separated), defaults to all of them
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+    csv_columns: Option<Vec<CsvColumn>>,
+
+    /// Leave out the header row of CSV output
+    #[clap(long)]
+    no_csv_header: bool,
+
+    /// Write the results to this file instead of stdout
+    #[clap(long, value_name = "FILE_PATH")]
+    out: Option<PathBuf>,
+
+    /// Least serious result that makes the run exit non-zero, defaults to warning
+    #[clap(long, value_enum, value_name = "LEVEL")]
+    fail_on: Option<FailOn>,
+
+    /// Report the results as a Nagios/Icinga plugin (nagios) rather than in the output
+    /// format, defaults to standard
+    #[clap(long, value_enum, value_name = "MODE")]
+    mode: Option<Mode>,
+
+    /// Serve Prometheus metrics on this address in daemon mode (e.g. 0.0.0.0:9115)
+    #[clap(long, value_name = "ADDRESS")]
+    metrics_address: Option<SocketAddr>,
 
     /// Path to a TOML configuration file
     #[clap(short, long, value_name = "FILE_PATH")]
     config_file: Option<PathBuf>,
 }
 
+impl CliArgs {
+    /// The mode asked for on the command line, in the environment or in the config
+    /// file, as far as it can be worked out when the configuration fails to build.
+    pub fn requested_mode(&self) -> Mode {
+        let env_var = |name| {
+            std::env::var(name)
+                .ok()
+                .filter(|v: &String| !v.trim().is_empty())
+        };
+        let from_file = || {
+            let path = self
+                .config_file
+                .clone()
+                .or_else(|| env_var(ENV_CONFIG_FILE).map(PathBuf::from))?;
+            read_toml_file(&path).ok()?.0.mode
+        };
+        self.mode
+            .or_else(|| parse_env_value(ENV_MODE, &env_var(ENV_MODE)?).ok())
+            .or_else(from_file)
+            .unwrap_or_default()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -117,14 +1248,47 @@ mod tests {
         file
     }
 
+    // Builds the config against a fixed set of environment variables
+    fn build_with_vars(args: CliArgs, vars: &[(&str, &str)]) -> Result<AppConfig, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        AppConfig::build_with_env(args, |name| vars.get(name).cloned())
+    }
+
+    fn days(days: u64) -> Duration {
+        HumanDuration::from_days(days).into()
+    }
+
     fn basic_cli_args() -> CliArgs {
         CliArgs {
             urls: None,
+            urls_from: None,
             error_days: None,
             warning_days: None,
             log_level: None,
             slack_webhook_url: None,
             check_frequency: None,
+            schedule: None,
+            timezone: None,
+            run_on_start: false,
+            no_run_on_start: false,
+            jitter: None,
+            connect_timeout: None,
+            handshake_timeout: None,
+            timeout: None,
+            retries: None,
+            retry_backoff: None,
+            concurrency: None,
+            per_host_concurrency: None,
+            output: None,
+            csv_columns: None,
+            no_csv_header: false,
+            out: None,
+            fail_on: None,
+            mode: None,
+            metrics_address: None,
             config_file: None,
         }
     }
@@ -133,21 +1297,41 @@ mod tests {
     fn build_config_cli_only() {
         let args = CliArgs {
             urls: Some(vec!["https://cli.com".to_string()]),
-            error_days: Some(5),
-            warning_days: Some(10),
+            urls_from: None,
+            error_days: Some(HumanDuration::from_days(5)),
+            warning_days: Some(HumanDuration::from_days(10)),
             log_level: Some("trace".to_string()),
             slack_webhook_url: Some("https://slack.cli.com".to_string()),
             check_frequency: None,
+            schedule: None,
+            timezone: None,
+            run_on_start: false,
+            no_run_on_start: false,
+            jitter: None,
+            connect_timeout: None,
+            handshake_timeout: None,
+            timeout: None,
+            retries: None,
+            retry_backoff: None,
+            concurrency: None,
+            per_host_concurrency: None,
+            output: None,
+            csv_columns: None,
+            no_csv_header: false,
+            out: None,
+            fail_on: None,
+            mode: None,
+            metrics_address: None,
             config_file: None,
         };
-        let config = AppConfig::build(args).unwrap();
+        let config = build_with_vars(args, &[]).unwrap();
         assert_eq!(config.urls, vec!["https://cli.com".to_string()]);
-        assert_eq!(config.error_days, 5);
-        assert_eq!(config.warning_days, 10);
+        assert_eq!(config.error_threshold, days(5));
+        assert_eq!(config.warning_threshold, days(10));
         assert_eq!(config.log_level, "trace");
         assert_eq!(
-            config.slack_webhook_url,
-            Some("https://slack.cli.com".to_string())
+            config.slack_webhook_url.as_ref().map(Secret::expose),
+            Some("https://slack.cli.com")
         );
     }
 
@@ -166,14 +1350,14 @@ mod tests {
             ..basic_cli_args()
         };
 
-        let config = AppConfig::build(args).unwrap();
+        let config = build_with_vars(args, &[]).unwrap();
         assert_eq!(config.urls, vec!["https://toml.com".to_string()]);
-        assert_eq!(config.error_days, 3);
-        assert_eq!(config.warning_days, 12);
+        assert_eq!(config.error_threshold, days(3));
+        assert_eq!(config.warning_threshold, days(12));
         assert_eq!(config.log_level, "warn");
         assert_eq!(
-            config.slack_webhook_url,
-            Some("https://slack.toml.com".to_string())
+            config.slack_webhook_url.as_ref().map(Secret::expose),
+            Some("https://slack.toml.com")
         );
     }
 
@@ -188,17 +1372,17 @@ mod tests {
         let temp_config_file = create_temp_toml_config(toml_content);
         let args = CliArgs {
             urls: Some(vec!["https://cli.com".to_string()]),
-            error_days: Some(5), // CLI overrides TOML's 3
+            error_days: Some(HumanDuration::from_days(5)), // CLI overrides TOML's 3
             // warning_days will come from TOML
             log_level: Some("debug".to_string()), // CLI overrides TOML's error
             config_file: Some(temp_config_file.path().to_path_buf()),
             ..basic_cli_args()
         };
 
-        let config = AppConfig::build(args).unwrap();
+        let config = build_with_vars(args, &[]).unwrap();
         assert_eq!(config.urls, vec!["https://cli.com".to_string()]);
-        assert_eq!(config.error_days, 5);
-        assert_eq!(config.warning_days, 12); // From TOML
+        assert_eq!(config.error_threshold, days(5));
+        assert_eq!(config.warning_threshold, days(12)); // From TOML
         assert_eq!(config.log_level, "debug");
     }
 
@@ -208,10 +1392,10 @@ mod tests {
             urls: Some(vec!["https://default.com".to_string()]), // Only URL provided
             ..basic_cli_args()
         };
-        let config = AppConfig::build(args).unwrap();
+        let config = build_with_vars(args, &[]).unwrap();
         assert_eq!(config.urls, vec!["https://default.com".to_string()]);
-        assert_eq!(config.error_days, DEFAULT_ERROR_DAYS);
-        assert_eq!(config.warning_days, DEFAULT_WARNING_DAYS);
+        assert_eq!(config.error_threshold, days(DEFAULT_ERROR_DAYS));
+        assert_eq!(config.warning_threshold, days(DEFAULT_WARNING_DAYS));
         assert_eq!(config.log_level, DEFAULT_LOG_LEVEL);
         assert_eq!(config.slack_webhook_url, None);
     }
@@ -219,7 +1403,7 @@ mod tests {
     #[test]
     fn build_config_error_missing_urls() {
         let args = basic_cli_args(); // No URLs anywhere
-        let result = AppConfig::build(args);
+        let result = build_with_vars(args, &[]);
         assert!(matches!(result, Err(ConfigError::MissingUrls)));
     }
 
@@ -230,7 +1414,7 @@ mod tests {
             config_file: Some(non_existent_path.clone()),
             ..basic_cli_args() // No URLs, but FileNotFound should take precedence
         };
-        let result = AppConfig::build(args);
+        let result = build_with_vars(args, &[]);
         match result {
             Err(ConfigError::FileNotFound(path)) => assert_eq!(path, non_existent_path),
             _ => panic!("Expected FileNotFound error"),
@@ -245,7 +1429,7 @@ mod tests {
             config_file: Some(temp_config_file.path().to_path_buf()),
             ..basic_cli_args()
         };
-        let result = AppConfig::build(args);
+        let result = build_with_vars(args, &[]);
         match result {
             Err(ConfigError::TomlParseError(path, _)) => assert_eq!(path, temp_config_file.path()),
             _ => panic!("Expected TomlParseError"),
@@ -262,7 +1446,767 @@ mod tests {
             config_file: Some(temp_config_file.path().to_path_buf()),
             ..basic_cli_args()
         };
-        let result = AppConfig::build(args);
+        let result = build_with_vars(args, &[]);
         assert!(matches!(result, Err(ConfigError::MissingUrls)));
     }
+
+    #[test]
+    fn build_config_urls_from_file() {
+        let urls_file =
+            create_temp_toml_config("https://file-a.com\n# comment\nhttps://file-b.com");
+        let args = CliArgs {
+            urls_from: Some(urls_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[]).unwrap();
+        assert_eq!(
+            config.urls,
+            vec!["https://file-a.com", "https://file-b.com"]
+        );
+        assert!(config.duplicate_urls.is_empty());
+    }
+
+    #[test]
+    fn build_config_cli_urls_and_urls_from_are_combined() {
+        let urls_file = create_temp_toml_config("https://file.com\nhttps://cli.com");
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            urls_from: Some(urls_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[]).unwrap();
+        assert_eq!(config.urls, vec!["https://cli.com", "https://file.com"]);
+        assert_eq!(config.duplicate_urls.len(), 1);
+        assert_eq!(config.duplicate_urls[0].url, "https://cli.com");
+        assert_eq!(
+            config.duplicate_urls[0].first_source,
+            ConfigSource::CommandLine
+        );
+        assert_eq!(
+            config.duplicate_urls[0].duplicate_source,
+            ConfigSource::UrlsFile {
+                path: urls_file.path().to_path_buf(),
+                line: 2
+            }
+        );
+    }
+
+    #[test]
+    fn build_config_duplicate_in_config_file_points_at_the_repeat() {
+        let config_file = create_temp_toml_config(
+            "urls = [\n  \"https://a.com\",\n  \"https://b.com\",\n]\n\n[[targets]]\nurl = \"https://a.com\"\n",
+        );
+        let path = config_file.path().to_path_buf();
+        let args = CliArgs {
+            config_file: Some(path.clone()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[]).unwrap();
+        assert_eq!(config.duplicate_urls.len(), 1);
+        assert_eq!(
+            config.duplicate_urls[0].first_source,
+            ConfigSource::ConfigFile {
+                path: path.clone(),
+                line: Some(2)
+            }
+        );
+        assert_eq!(
+            config.duplicate_urls[0].duplicate_source,
+            ConfigSource::ConfigFile {
+                path,
+                line: Some(7)
+            }
+        );
+    }
+
+    #[test]
+    fn build_config_include_merges_fragments() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        fs::create_dir(dir.path().join("conf.d")).unwrap();
+        fs::write(
+            dir.path().join("conf.d/a.toml"),
+            "urls = [\"https://a.com\"]\nwarning_days = 20\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("conf.d/b.toml"),
+            "urls = [\"https://b.com\", \"https://main.com\"]\nwarning_days = 40\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("targets.txt"), "https://list.com\n").unwrap();
+        let main_path = dir.path().join("config.toml");
+        fs::write(
+            &main_path,
+            "include = [\"conf.d/*.toml\"]\nurls_from = \"targets.txt\"\nurls = [\"https://main.com\"]\nerror_days = 7\n",
+        )
+        .unwrap();
+
+        let args = CliArgs {
+            config_file: Some(main_path.clone()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[]).unwrap();
+        assert_eq!(
+            config.urls,
+            vec![
+                "https://main.com",
+                "https://list.com",
+                "https://a.com",
+                "https://b.com"
+            ]
+        );
+        assert_eq!(config.error_threshold, days(7)); // From main file
+        assert_eq!(config.warning_threshold, days(20)); // From first fragment
+        assert_eq!(config.duplicate_urls.len(), 1);
+        assert_eq!(
+            config.duplicate_urls[0].first_source,
+            ConfigSource::ConfigFile {
+                path: main_path,
+                line: Some(3)
+            }
+        );
+    }
+
+    #[test]
+    fn build_config_error_nested_include() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        fs::write(dir.path().join("fragment.toml"), "include = [\"*.conf\"]\n").unwrap();
+        let main_path = dir.path().join("config.toml");
+        fs::write(&main_path, "include = [\"fragment.toml\"]\n").unwrap();
+
+        let args = CliArgs {
+            config_file: Some(main_path),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[]);
+        assert!(matches!(result, Err(ConfigError::NestedInclude(_))));
+    }
+
+    #[test]
+    fn build_config_env_only() {
+        let args = basic_cli_args();
+        let vars = [
+            (ENV_URLS, "https://env-a.com, https://env-b.com"),
+            (ENV_ERROR_DAYS, "4"),
+            (ENV_WARNING_DAYS, "8"),
+            (ENV_LOG_LEVEL, "debug"),
+            (ENV_CHECK_FREQUENCY, "2"),
+            (ENV_SLACK_WEBHOOK_URL, "https://slack.env.com"),
+        ];
+        let config = build_with_vars(args, &vars).unwrap();
+        assert_eq!(config.urls, vec!["https://env-a.com", "https://env-b.com"]);
+        assert_eq!(config.error_threshold, days(4));
+        assert_eq!(config.warning_threshold, days(8));
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.schedule, Some(Schedule::Interval(days(2))));
+        assert_eq!(
+            config.slack_webhook_url.as_ref().map(Secret::expose),
+            Some("https://slack.env.com")
+        );
+    }
+
+    #[test]
+    fn build_config_output_format() {
+        let temp_config_file = create_temp_toml_config(
+            r#"
+            urls = ["https://toml.com"]
+            output = "json"
+        "#,
+        );
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args.clone(), &[]).unwrap();
+        assert_eq!(config.output.format, OutputFormat::Json);
+
+        let config = build_with_vars(args, &[(ENV_OUTPUT, "NDJSON")]).unwrap();
+        assert_eq!(config.output.format, OutputFormat::Ndjson);
+
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            ..basic_cli_args()
+        };
+        assert_eq!(
+            build_with_vars(args, &[]).unwrap().output,
+            OutputOptions::default()
+        );
+    }
+
+    #[test]
+    fn build_config_csv_options() {
+        let temp_config_file = create_temp_toml_config(
+            r#"
+            urls = ["https://toml.com"]
+            output = "csv"
+            csv_columns = ["url", "not_after"]
+            csv_header = false
+        "#,
+        );
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args.clone(), &[]).unwrap();
+        assert_eq!(
+            config.output.csv_columns,
+            vec![CsvColumn::Url, CsvColumn::NotAfter]
+        );
+        assert!(!config.output.csv_header);
+
+        let vars = [
+            (ENV_CSV_COLUMNS, "url, sans ,error"),
+            (ENV_CSV_HEADER, "true"),
+        ];
+        let config = build_with_vars(args, &vars).unwrap();
+        assert_eq!(
+            config.output.csv_columns,
+            vec![CsvColumn::Url, CsvColumn::Sans, CsvColumn::Error]
+        );
+        assert!(config.output.csv_header);
+
+        let vars = [
+            (ENV_URLS, "https://env.com"),
+            (ENV_CSV_COLUMNS, "url,colour"),
+        ];
+        assert!(matches!(
+            build_with_vars(basic_cli_args(), &vars),
+            Err(ConfigError::EnvVarParseError(name, _)) if name == ENV_CSV_COLUMNS
+        ));
+    }
+
+    #[test]
+    fn build_config_fail_on() {
+        let temp_config_file = create_temp_toml_config(
+            r#"
+            urls = ["https://toml.com"]
+            fail_on = "error"
+        "#,
+        );
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        assert_eq!(
+            build_with_vars(args.clone(), &[]).unwrap().fail_on,
+            FailOn::Error
+        );
+        let config = build_with_vars(args.clone(), &[(ENV_FAIL_ON, "Warning")]).unwrap();
+        assert_eq!(config.fail_on, FailOn::Warning);
+
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            ..basic_cli_args()
+        };
+        assert_eq!(build_with_vars(args, &[]).unwrap().fail_on, FailOn::Warning);
+        assert!(matches!(
+            build_with_vars(basic_cli_args(), &[(ENV_FAIL_ON, "critical")]),
+            Err(ConfigError::EnvVarParseError(name, _)) if name == ENV_FAIL_ON
+        ));
+    }
+
+    #[test]
+    fn build_config_mode() {
+        let temp_config_file = create_temp_toml_config(
+            r#"
+            urls = ["https://toml.com"]
+            mode = "nagios"
+        "#,
+        );
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        assert_eq!(
+            build_with_vars(args.clone(), &[]).unwrap().mode,
+            Mode::Nagios
+        );
+        let config = build_with_vars(args, &[(ENV_MODE, "standard")]).unwrap();
+        assert_eq!(config.mode, Mode::Standard);
+
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            mode: Some(Mode::Nagios),
+            ..basic_cli_args()
+        };
+        assert_eq!(build_with_vars(args, &[]).unwrap().mode, Mode::Nagios);
+    }
+
+    #[test]
+    fn requested_mode_survives_invalid_config() {
+        let temp_config_file = create_temp_toml_config(
+            r#"
+            mode = "nagios"
+            concurrency = 0
+        "#,
+        );
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        assert!(build_with_vars(args.clone(), &[]).is_err());
+        assert_eq!(args.requested_mode(), Mode::Nagios);
+        assert_eq!(basic_cli_args().requested_mode(), Mode::Standard);
+    }
+
+    #[test]
+    fn build_config_precedence_cli_env_toml_defaults() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            error_days = 3
+            warning_days = 12
+            log_level = "error"
+            slack_webhook_url = "https://slack.toml.com"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            error_days: Some(HumanDuration::from_days(5)), // CLI overrides env and TOML
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let vars = [
+            (ENV_URLS, "https://env.com"),
+            (ENV_ERROR_DAYS, "4"),
+            (ENV_WARNING_DAYS, "8"), // Env overrides TOML
+        ];
+
+        let config = build_with_vars(args, &vars).unwrap();
+        assert_eq!(config.urls, vec!["https://env.com"]); // Env overrides TOML
+        assert_eq!(config.error_threshold, days(5));
+        assert_eq!(config.warning_threshold, days(8));
+        assert_eq!(config.log_level, "error"); // From TOML
+        assert_eq!(
+            config.slack_webhook_url.as_ref().map(Secret::expose),
+            Some("https://slack.toml.com")
+        );
+        assert_eq!(config.schedule, None); // Default
+    }
+
+    #[test]
+    fn build_config_cli_urls_override_env() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[(ENV_URLS, "https://env.com")]).unwrap();
+        assert_eq!(config.urls, vec!["https://cli.com"]);
+    }
+
+    #[test]
+    fn build_config_config_file_from_env() {
+        let temp_config_file = create_temp_toml_config(r#"urls = ["https://toml.com"]"#);
+        let path = temp_config_file.path().to_str().unwrap();
+        let config = build_with_vars(basic_cli_args(), &[(ENV_CONFIG_FILE, path)]).unwrap();
+        assert_eq!(config.urls, vec!["https://toml.com"]);
+    }
+
+    #[test]
+    fn build_config_empty_env_var_is_ignored() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[(ENV_WARNING_DAYS, "")]).unwrap();
+        assert_eq!(config.warning_threshold, days(DEFAULT_WARNING_DAYS));
+    }
+
+    #[test]
+    fn build_config_error_env_parse_error() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[(ENV_WARNING_DAYS, "soon")]);
+        match result {
+            Err(ConfigError::EnvVarParseError(name, value)) => {
+                assert_eq!(name, ENV_WARNING_DAYS);
+                assert_eq!(value, "soon");
+            }
+            _ => panic!("Expected EnvVarParseError"),
+        }
+    }
+
+    #[test]
+    fn build_config_error_zero_concurrency() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[(ENV_PER_HOST_CONCURRENCY, "0")]);
+        match result {
+            Err(ConfigError::InvalidValue(setting, _)) => {
+                assert_eq!(setting, "per_host_concurrency");
+            }
+            _ => panic!("Expected InvalidValue"),
+        }
+    }
+
+    #[test]
+    fn build_config_error_zero_intervals() {
+        for check_frequency in ["0", "0s"] {
+            let args = CliArgs {
+                urls: Some(vec!["https://cli.com".to_string()]),
+                ..basic_cli_args()
+            };
+            let result = build_with_vars(args, &[(ENV_CHECK_FREQUENCY, check_frequency)]);
+            match result {
+                Err(ConfigError::InvalidValue(setting, _)) => {
+                    assert_eq!(setting, "check_frequency");
+                }
+                other => panic!("Expected InvalidValue, got {:?}", other),
+            }
+        }
+
+        let temp_config_file = create_temp_toml_config(
+            "check_frequency = \"1h\"\n\n[[targets]]\nurl = \"https://critical.com\"\ninterval = 0\n",
+        );
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        match build_with_vars(args, &[]) {
+            Err(ConfigError::InvalidValue(setting, _)) => {
+                assert_eq!(setting, "interval of https://critical.com");
+            }
+            other => panic!("Expected InvalidValue, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn build_config_resolves_secret_reference() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            slack_webhook_url = "env:WEBHOOK_SECRET"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let vars = [("WEBHOOK_SECRET", "https://slack.secret.com")];
+
+        let config = build_with_vars(args, &vars).unwrap();
+        assert_eq!(
+            config.slack_webhook_url.as_ref().map(Secret::expose),
+            Some("https://slack.secret.com")
+        );
+        assert!(!format!("{:?}", config).contains("https://slack.secret.com"));
+    }
+
+    #[test]
+    fn build_config_error_unresolvable_secret() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            slack_webhook_url: Some("env:WEBHOOK_SECRET".to_string()),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[]);
+        match result {
+            Err(ConfigError::SecretResolveError(setting, _)) => {
+                assert_eq!(setting, "slack_webhook_url")
+            }
+            _ => panic!("Expected SecretResolveError"),
+        }
+    }
+
+    #[test]
+    fn build_config_records_setting_sources() {
+        let toml_content = "urls = [\"https://toml.com\"]\nwarning_days = 12\nerror_days = 3\n";
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let path = temp_config_file.path().to_path_buf();
+        let args = CliArgs {
+            error_days: Some(HumanDuration::from_days(5)),
+            config_file: Some(path.clone()),
+            ..basic_cli_args()
+        };
+
+        let config = build_with_vars(args, &[(ENV_LOG_LEVEL, "debug")]).unwrap();
+        let settings = &config.sources.settings;
+        assert_eq!(config.sources.files, vec![path.clone()]);
+        assert_eq!(settings.get("error_days"), Some(&ConfigSource::CommandLine));
+        assert_eq!(
+            settings.get("log_level"),
+            Some(&ConfigSource::Environment {
+                name: ENV_LOG_LEVEL.to_string()
+            })
+        );
+        assert_eq!(
+            settings.get("warning_days"),
+            Some(&ConfigSource::ConfigFile {
+                path: path.clone(),
+                line: Some(2)
+            })
+        );
+        assert_eq!(settings.get("check_frequency"), None);
+        assert_eq!(
+            config.sources.urls.get("https://toml.com"),
+            Some(&ConfigSource::ConfigFile {
+                path,
+                line: Some(1)
+            })
+        );
+    }
+
+    #[test]
+    fn find_key_line_ignores_similar_keys() {
+        let content = "warning_days_extra = 1\n  warning_days = 2\n";
+        assert_eq!(find_key_line(content, "warning_days"), Some(2));
+        assert_eq!(find_key_line(content, "error_days"), None);
+    }
+
+    #[test]
+    fn build_config_duration_strings() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            error_days = "36h"
+            warning_days = 10
+            check_frequency = "15m"
+            timeout = "30s"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+
+        let config = build_with_vars(args, &[(ENV_WARNING_DAYS, "6w")]).unwrap();
+        assert_eq!(config.error_threshold, Duration::from_secs(36 * 60 * 60));
+        assert_eq!(config.warning_threshold, days(42)); // Env overrides TOML
+        assert_eq!(
+            config.schedule,
+            Some(Schedule::Interval(Duration::from_secs(15 * 60)))
+        );
+        assert_eq!(config.timeouts.overall, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn build_config_error_invalid_duration() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            check_frequency = "often"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[]);
+        assert!(matches!(result, Err(ConfigError::TomlParseError(..))));
+    }
+
+    #[test]
+    fn build_config_cron_schedule() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            schedule = "0 8 * * MON-FRI"
+            timezone = "Europe/London"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+
+        let config = build_with_vars(args, &[]).unwrap();
+        assert_eq!(
+            config.schedule,
+            Some(Schedule::cron("0 8 * * MON-FRI", chrono_tz::Europe::London).unwrap())
+        );
+        assert!(!config.run_on_start);
+    }
+
+    #[test]
+    fn build_config_run_on_start_defaults() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            check_frequency: Some(HumanDuration::from_days(1)),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[]).unwrap();
+        assert!(config.run_on_start);
+
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            schedule: Some("0 8 * * *".to_string()),
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[(ENV_RUN_ON_START, "true")]).unwrap();
+        assert!(config.run_on_start);
+    }
+
+    #[test]
+    fn build_config_no_run_on_start_overrides_lower_layers() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            check_frequency: Some(HumanDuration::from_days(1)),
+            no_run_on_start: true,
+            ..basic_cli_args()
+        };
+        let config = build_with_vars(args, &[(ENV_RUN_ON_START, "true")]).unwrap();
+        assert!(!config.run_on_start);
+
+        let result = Cli::try_parse_from([
+            "ssl_checker",
+            "--run-on-start",
+            "--no-run-on-start",
+            "--urls",
+            "https://cli.com",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_config_schedule_overrides_lower_layer_frequency() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            check_frequency = 1
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+
+        let config = build_with_vars(args, &[(ENV_SCHEDULE, "0 8 * * *")]).unwrap();
+        assert!(matches!(config.schedule, Some(Schedule::Cron { .. })));
+    }
+
+    #[test]
+    fn build_config_error_conflicting_schedules() {
+        let toml_content = r#"
+            urls = ["https://toml.com"]
+            check_frequency = 1
+            schedule = "0 8 * * *"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[]);
+        assert!(matches!(result, Err(ConfigError::InvalidSchedule(_))));
+    }
+
+    #[test]
+    fn build_config_error_invalid_cron_and_timezone() {
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            schedule: Some("every morning".to_string()),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[]);
+        assert!(matches!(result, Err(ConfigError::InvalidSchedule(_))));
+
+        let args = CliArgs {
+            urls: Some(vec!["https://cli.com".to_string()]),
+            schedule: Some("0 8 * * *".to_string()),
+            timezone: Some("Mars/Olympus_Mons".to_string()),
+            ..basic_cli_args()
+        };
+        let result = build_with_vars(args, &[]);
+        assert!(matches!(result, Err(ConfigError::InvalidSchedule(_))));
+    }
+
+    #[test]
+    fn build_config_target_tables() {
+        let toml_content = r#"
+            urls = ["https://daily.com"]
+            check_frequency = "1d"
+            jitter = "10m"
+
+            [[targets]]
+            url = "https://critical.com"
+            interval = "1h"
+            jitter = "1m"
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+
+        let config = build_with_vars(args, &[]).unwrap();
+        assert_eq!(
+            config.urls,
+            vec!["https://daily.com", "https://critical.com"]
+        );
+        assert_eq!(
+            config.schedule_for("https://critical.com"),
+            Some(Schedule::Interval(Duration::from_secs(60 * 60)))
+        );
+        assert_eq!(
+            config.schedule_for("https://daily.com"),
+            Some(Schedule::Interval(days(1)))
+        );
+        assert_eq!(
+            config.jitter_for("https://critical.com"),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            config.jitter_for("https://daily.com"),
+            Duration::from_secs(10 * 60)
+        );
+        assert_eq!(
+            config.sources.urls["https://critical.com"],
+            ConfigSource::ConfigFile {
+                path: temp_config_file.path().to_path_buf(),
+                line: Some(7),
+            }
+        );
+    }
+
+    #[test]
+    fn build_config_error_invalid_tag_names() {
+        // Reserved and invalid label names, and the target label, are rejected
+        for tags in [
+            r#"{ __name__ = "up" }"#,
+            r#"{ cost-centre = "42" }"#,
+            r#"{ target = "x" }"#,
+        ] {
+            let temp_config_file = create_temp_toml_config(&format!(
+                "[[targets]]\nurl = \"https://critical.com\"\ntags = {}\n",
+                tags
+            ));
+            let args = CliArgs {
+                config_file: Some(temp_config_file.path().to_path_buf()),
+                ..basic_cli_args()
+            };
+            match build_with_vars(args, &[]) {
+                Err(ConfigError::InvalidTag(url, _)) => assert_eq!(url, "https://critical.com"),
+                other => panic!("Expected InvalidTag for {}, got {:?}", tags, other),
+            }
+        }
+    }
+
+    #[test]
+    fn build_config_metrics_address_and_tags() {
+        let toml_content = r#"
+            check_frequency = "1h"
+            metrics_address = "127.0.0.1:9115"
+
+            [[targets]]
+            url = "https://critical.com"
+            tags = { team = "payments", env = "prod" }
+        "#;
+        let temp_config_file = create_temp_toml_config(toml_content);
+        let args = CliArgs {
+            config_file: Some(temp_config_file.path().to_path_buf()),
+            ..basic_cli_args()
+        };
+
+        let config = build_with_vars(args.clone(), &[]).unwrap();
+        assert_eq!(
+            config.metrics_address,
+            Some("127.0.0.1:9115".parse().unwrap())
+        );
+        let tags = &config.target_options["https://critical.com"].tags;
+        assert_eq!(tags.get("team").map(String::as_str), Some("payments"));
+        assert_eq!(tags.get("env").map(String::as_str), Some("prod"));
+
+        let config = build_with_vars(args, &[(ENV_METRICS_ADDRESS, "0.0.0.0:9200")]).unwrap();
+        assert_eq!(
+            config.metrics_address,
+            Some("0.0.0.0:9200".parse().unwrap())
+        );
+    }
 }
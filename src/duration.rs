@@ -0,0 +1,198 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Deserializer, de};
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+const SECONDS_PER_WEEK: u64 = 7 * SECONDS_PER_DAY;
+
+// Largest unit first, used when formatting
+const DISPLAY_UNITS: [(&str, u64); 4] = [
+    ("d", SECONDS_PER_DAY),
+    ("h", SECONDS_PER_HOUR),
+    ("m", SECONDS_PER_MINUTE),
+    ("s", 1),
+];
+
+// --- Duration setting that accepts "15m", "6h", "14d" or a whole number of days ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HumanDuration(pub Duration);
+
+impl HumanDuration {
+    /// Panics if the number of seconds overflows, so it's meant for constants; use
+    /// `checked_from_days` for values that come from input.
+    pub fn from_days(days: u64) -> Self {
+        Self::checked_from_days(days).expect("number of days is too large")
+    }
+
+    pub fn checked_from_days(days: u64) -> Option<Self> {
+        let seconds = days.checked_mul(SECONDS_PER_DAY)?;
+        Some(Self(Duration::from_secs(seconds)))
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    /// Parses one or more `<number><unit>` pairs (units `s`, `m`, `h`, `d`, `w`), such
+    /// as `90m` or `1d12h`. A bare number is a number of days, for compatibility
+    /// with the integer settings.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let invalid = || {
+            format!(
+                "invalid duration '{}' - expected a number of days or a value such as 15m, 6h or 14d",
+                value
+            )
+        };
+
+        if let Ok(days) = value.parse::<u64>() {
+            return Self::checked_from_days(days).ok_or_else(invalid);
+        }
+        if value.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut total_seconds: u64 = 0;
+        let mut rest = value;
+        while !rest.is_empty() {
+            let digits_end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .ok_or_else(invalid)?;
+            let (number, remainder) = rest.split_at(digits_end);
+            let number: u64 = number.parse().map_err(|_| invalid())?;
+
+            let unit_end = remainder
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(remainder.len());
+            let (unit, remainder) = remainder.split_at(unit_end);
+            let unit_seconds = match unit.trim() {
+                "s" => 1,
+                "m" => SECONDS_PER_MINUTE,
+                "h" => SECONDS_PER_HOUR,
+                "d" => SECONDS_PER_DAY,
+                "w" => SECONDS_PER_WEEK,
+                _ => return Err(invalid()),
+            };
+
+            total_seconds = number
+                .checked_mul(unit_seconds)
+                .and_then(|seconds| total_seconds.checked_add(seconds))
+                .ok_or_else(invalid)?;
+            rest = remainder.trim_start();
+        }
+
+        Ok(Self(Duration::from_secs(total_seconds)))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut remaining = self.0.as_secs();
+        if remaining == 0 {
+            return write!(f, "0s");
+        }
+        for (unit, unit_seconds) in DISPLAY_UNITS {
+            if remaining >= unit_seconds {
+                write!(f, "{}{}", remaining / unit_seconds, unit)?;
+                remaining %= unit_seconds;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HumanDurationVisitor;
+
+        impl de::Visitor<'_> for HumanDurationVisitor {
+            type Value = HumanDuration;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number of days or a duration string such as \"6h\"")
+            }
+
+            fn visit_i64<E: de::Error>(self, days: i64) -> Result<Self::Value, E> {
+                let days =
+                    u64::try_from(days).map_err(|_| E::custom("duration must not be negative"))?;
+                self.visit_u64(days)
+            }
+
+            fn visit_u64<E: de::Error>(self, days: u64) -> Result<Self::Value, E> {
+                HumanDuration::checked_from_days(days)
+                    .ok_or_else(|| E::custom(format!("{} days is too long a duration", days)))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(HumanDurationVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        let parse = |value: &str| value.parse::<HumanDuration>().unwrap().0;
+        assert_eq!(parse("15m"), Duration::from_secs(15 * 60));
+        assert_eq!(parse("6h"), Duration::from_secs(6 * 60 * 60));
+        assert_eq!(parse("14d"), Duration::from_secs(14 * SECONDS_PER_DAY));
+        assert_eq!(parse("2w"), Duration::from_secs(14 * SECONDS_PER_DAY));
+        assert_eq!(parse("1d12h"), Duration::from_secs(36 * 60 * 60));
+        assert_eq!(parse("30s"), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn parse_duration_bare_number_is_days() {
+        assert_eq!("14".parse(), Ok(HumanDuration::from_days(14)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_values() {
+        for value in ["", "d", "6x", "-1d", "1.5h", "h6"] {
+            assert!(value.parse::<HumanDuration>().is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflowing_days() {
+        assert!("300000000000000".parse::<HumanDuration>().is_err());
+        assert!(format!("{}", u64::MAX).parse::<HumanDuration>().is_err());
+        let error = HumanDuration::deserialize(toml::Value::Integer(300000000000000)).unwrap_err();
+        assert!(error.to_string().contains("too long"), "{}", error);
+    }
+
+    #[test]
+    fn display_duration() {
+        assert_eq!(HumanDuration::from_days(14).to_string(), "14d");
+        assert_eq!("36h".parse::<HumanDuration>().unwrap().to_string(), "1d12h");
+        assert_eq!("90m".parse::<HumanDuration>().unwrap().to_string(), "1h30m");
+        assert_eq!(HumanDuration(Duration::ZERO).to_string(), "0s");
+    }
+
+    #[test]
+    fn deserialize_integer_and_string() {
+        #[derive(Deserialize)]
+        struct Settings {
+            days: HumanDuration,
+            text: HumanDuration,
+        }
+        let settings: Settings = toml::from_str("days = 3\ntext = \"6h\"").unwrap();
+        assert_eq!(settings.days, HumanDuration::from_days(3));
+        assert_eq!(settings.text.0, Duration::from_secs(6 * 60 * 60));
+        assert!(toml::from_str::<Settings>("days = -3\ntext = \"6h\"").is_err());
+    }
+}
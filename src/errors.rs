@@ -1,4 +1,11 @@
-use std::{fmt, path::PathBuf};
+use std::{fmt, net::SocketAddr, path::PathBuf, time::Duration};
+
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::proto::op::ResponseCode;
+use rustls::AlertDescription;
+use serde::{Deserialize, Serialize};
+
+use crate::duration::HumanDuration;
 
 // --- Configuration Error Type ---
 #[derive(Debug)]
@@ -6,6 +13,13 @@ pub enum ConfigError {
     FileReadError(PathBuf, Box<std::io::Error>),
     TomlParseError(PathBuf, Box<toml::de::Error>),
     FileNotFound(PathBuf),
+    IncludePatternError(String, Box<glob::PatternError>),
+    NestedInclude(PathBuf),
+    EnvVarParseError(String, String), // Variable name and invalid value
+    SecretResolveError(String, String), // Setting name and reason
+    InvalidSchedule(String),
+    InvalidValue(String, String), // Setting name and reason
+    InvalidTag(String, String),   // Target URL and tag name
     MissingUrls,
 }
 
@@ -23,9 +37,36 @@ impl fmt::Display for ConfigError {
                 "Configuration file {:?} not found. Ensure the path is correct and the file exists.",
                 path
             ),
+            ConfigError::IncludePatternError(pattern, err) => {
+                write!(f, "Invalid include pattern '{}': {}", pattern, err)
+            }
+            ConfigError::NestedInclude(path) => write!(
+                f,
+                "Included config file {:?} cannot itself contain an 'include' field.",
+                path
+            ),
+            ConfigError::EnvVarParseError(name, value) => {
+                write!(
+                    f,
+                    "Invalid value '{}' for environment variable {}",
+                    value, name
+                )
+            }
+            ConfigError::SecretResolveError(setting, reason) => {
+                write!(f, "Failed to resolve secret for '{}': {}", setting, reason)
+            }
+            ConfigError::InvalidSchedule(reason) => write!(f, "Invalid schedule: {}", reason),
+            ConfigError::InvalidValue(setting, reason) => {
+                write!(f, "Invalid value for '{}': {}", setting, reason)
+            }
+            ConfigError::InvalidTag(url, name) => write!(
+                f,
+                "Invalid tag '{}' for '{}': tag names must be valid metric label names (letters, digits and underscores, not starting with a digit or '__') other than 'target'",
+                name, url
+            ),
             ConfigError::MissingUrls => write!(
                 f,
-                "No URLs provided. Please specify URLs via the --urls flag or in the 'urls' field of the configuration file."
+                "No URLs provided. Please specify URLs via the --urls or --urls-from flags, the SSL_CHECKER_URLS or SSL_CHECKER_URLS_FROM environment variables, or in the 'urls' or 'urls_from' fields of the configuration file."
             ),
         }
     }
@@ -36,21 +77,163 @@ impl std::error::Error for ConfigError {}
 // --- SSL Check Error Type ---
 #[derive(Debug)]
 pub enum SslCheckError {
-    NetworkError(reqwest::Error),
-    NoCertificatesFound(String),            // URL for context
     UrlParseError(String, url::ParseError), // Original URL string and error
+    DnsError {
+        host: String,
+        error: Box<ResolveError>,
+    },
+    ConnectionRefused {
+        address: SocketAddr,
+    },
+    // Any other failure to connect, or a connection dropped during the handshake
+    NetworkError {
+        address: SocketAddr,
+        error: std::io::Error,
+    },
+    TlsAlert {
+        address: SocketAddr,
+        alert: AlertDescription,
+    },
+    // A handshake failure that wasn't an alert from the server
+    TlsError {
+        address: SocketAddr,
+        error: rustls::Error,
+    },
+    Timeout {
+        stage: TimeoutStage,
+        limit: Duration,
+        // The address being connected to, if the timeout happened after resolving the host
+        address: Option<SocketAddr>,
+    },
+    NoCertificatesFound(String), // URL for context
+    InternalError(String), // A bug in the checker, such as a panic, rather than a problem with the target
+}
+
+// --- Kind of failure, as a stable identifier for reports ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidUrl,
+    Dns,
+    ConnectionRefused,
+    Network,
+    TlsAlert,
+    Tls,
+    Timeout,
+    NoCertificates,
+    Internal,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::InvalidUrl => "invalid_url",
+            ErrorKind::Dns => "dns",
+            ErrorKind::ConnectionRefused => "connection_refused",
+            ErrorKind::Network => "network",
+            ErrorKind::TlsAlert => "tls_alert",
+            ErrorKind::Tls => "tls",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::NoCertificates => "no_certificates",
+            ErrorKind::Internal => "internal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// --- Stage of a check that ran out of time ---
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutStage {
+    Dns,
+    Connect,
+    Handshake,
+    Overall,
+}
+
+impl fmt::Display for TimeoutStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutStage::Dns => write!(f, "dns"),
+            TimeoutStage::Connect => write!(f, "connect"),
+            TimeoutStage::Handshake => write!(f, "handshake"),
+            TimeoutStage::Overall => write!(f, "overall"),
+        }
+    }
+}
+
+// --- How serious a failed check is ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Probably transient, such as a timeout or a dropped connection
+    Warning,
+    /// The check couldn't be carried out, such as an invalid URL or a host that doesn't resolve
+    Error,
+    /// The server is reachable but clients can't establish a TLS connection to it
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
 }
 
 impl fmt::Display for SslCheckError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SslCheckError::NetworkError(err) => write!(f, "Network error: {}", err),
-            SslCheckError::NoCertificatesFound(url) => {
-                write!(f, "No SSL certificates found for URL: {}", url)
-            }
             SslCheckError::UrlParseError(url, err) => {
                 write!(f, "Failed to parse URL '{}': {}", url, err)
             }
+            SslCheckError::DnsError { host, error } => {
+                write!(f, "DNS lookup for '{}' failed: {}", host, error)
+            }
+            SslCheckError::ConnectionRefused { address } => {
+                write!(f, "Connection refused by {}", address)
+            }
+            SslCheckError::NetworkError { address, error } => {
+                write!(f, "Network error from {}: {}", address, error)
+            }
+            SslCheckError::TlsAlert { address, alert } => write!(
+                f,
+                "TLS handshake alert from {}: {} ({})",
+                address,
+                alert_name(*alert),
+                u8::from(*alert)
+            ),
+            SslCheckError::TlsError { address, error } => {
+                write!(f, "TLS handshake with {} failed: {}", address, error)
+            }
+            SslCheckError::Timeout {
+                stage,
+                limit,
+                address: Some(address),
+            } => write!(
+                f,
+                "Timed out after {} ({} timeout) connecting to {}",
+                HumanDuration(*limit),
+                stage,
+                address
+            ),
+            SslCheckError::Timeout {
+                stage,
+                limit,
+                address: None,
+            } => write!(
+                f,
+                "Timed out after {} ({} timeout)",
+                HumanDuration(*limit),
+                stage
+            ),
+            SslCheckError::NoCertificatesFound(url) => {
+                write!(f, "No SSL certificates found for URL: {}", url)
+            }
+            SslCheckError::InternalError(message) => write!(f, "Internal error: {}", message),
         }
     }
 }
@@ -58,9 +241,195 @@ impl fmt::Display for SslCheckError {
 impl std::error::Error for SslCheckError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            SslCheckError::NetworkError(err) => Some(err),
             SslCheckError::UrlParseError(_, err) => Some(err),
+            SslCheckError::DnsError { error, .. } => Some(error.as_ref()),
+            SslCheckError::NetworkError { error, .. } => Some(error),
+            SslCheckError::TlsError { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl SslCheckError {
+    /// Whether the failure may be transient, so that trying again could succeed.
+    /// Timeouts, dropped connections and DNS server failures are retryable; invalid
+    /// URLs, unknown hosts, refused connections and TLS failures are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SslCheckError::Timeout { .. } => true,
+            SslCheckError::DnsError { error, .. } => match error.kind() {
+                ResolveErrorKind::NoRecordsFound { response_code, .. } => {
+                    *response_code == ResponseCode::ServFail
+                }
+                ResolveErrorKind::Timeout
+                | ResolveErrorKind::Io(_)
+                | ResolveErrorKind::Proto(_)
+                | ResolveErrorKind::NoConnections => true,
+                _ => false,
+            },
+            SslCheckError::NetworkError { error, .. } => matches!(
+                error.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+            ),
+            SslCheckError::UrlParseError(..)
+            | SslCheckError::ConnectionRefused { .. }
+            | SslCheckError::TlsAlert { .. }
+            | SslCheckError::TlsError { .. }
+            | SslCheckError::NoCertificatesFound(_)
+            | SslCheckError::InternalError(_) => false,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SslCheckError::UrlParseError(..) => ErrorKind::InvalidUrl,
+            SslCheckError::DnsError { .. } => ErrorKind::Dns,
+            SslCheckError::ConnectionRefused { .. } => ErrorKind::ConnectionRefused,
+            SslCheckError::NetworkError { .. } => ErrorKind::Network,
+            SslCheckError::TlsAlert { .. } => ErrorKind::TlsAlert,
+            SslCheckError::TlsError { .. } => ErrorKind::Tls,
+            SslCheckError::Timeout { .. } => ErrorKind::Timeout,
+            SslCheckError::NoCertificatesFound(_) => ErrorKind::NoCertificates,
+            SslCheckError::InternalError(_) => ErrorKind::Internal,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            SslCheckError::Timeout { .. } | SslCheckError::NetworkError { .. } => Severity::Warning,
+            SslCheckError::UrlParseError(..)
+            | SslCheckError::DnsError { .. }
+            | SslCheckError::InternalError(_) => Severity::Error,
+            SslCheckError::ConnectionRefused { .. }
+            | SslCheckError::TlsAlert { .. }
+            | SslCheckError::TlsError { .. }
+            | SslCheckError::NoCertificatesFound(_) => Severity::Critical,
+        }
+    }
+
+    /// The address that was being connected to when the check failed, if known.
+    pub fn address(&self) -> Option<SocketAddr> {
+        match self {
+            SslCheckError::ConnectionRefused { address }
+            | SslCheckError::NetworkError { address, .. }
+            | SslCheckError::TlsAlert { address, .. }
+            | SslCheckError::TlsError { address, .. } => Some(*address),
+            SslCheckError::Timeout { address, .. } => *address,
+            _ => None,
+        }
+    }
+
+    /// The TLS alert code sent by the server, if the handshake was rejected with one.
+    pub fn alert_code(&self) -> Option<u8> {
+        match self {
+            SslCheckError::TlsAlert { alert, .. } => Some(u8::from(*alert)),
             _ => None,
         }
     }
 }
+
+/// Formats an alert the way it is named in the TLS specifications, e.g. `handshake_failure`.
+pub fn alert_name(alert: AlertDescription) -> String {
+    let mut name = String::new();
+    for (idx, c) in format!("{:?}", alert).chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if idx > 0 {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn address() -> SocketAddr {
+        "192.0.2.10:443".parse().unwrap()
+    }
+
+    fn dns_error(response_code: ResponseCode) -> SslCheckError {
+        let error = ResolveError::from(ResolveErrorKind::NoRecordsFound {
+            query: Box::default(),
+            soa: None,
+            negative_ttl: None,
+            response_code,
+            trusted: false,
+        });
+        SslCheckError::DnsError {
+            host: "example.com".to_string(),
+            error: Box::new(error),
+        }
+    }
+
+    #[test]
+    fn transient_errors_are_retryable() {
+        let reset = SslCheckError::NetworkError {
+            address: address(),
+            error: io::Error::from(io::ErrorKind::ConnectionReset),
+        };
+        assert!(reset.is_retryable());
+        let timeout = SslCheckError::Timeout {
+            stage: TimeoutStage::Handshake,
+            limit: Duration::from_secs(10),
+            address: Some(address()),
+        };
+        assert!(timeout.is_retryable());
+        assert!(dns_error(ResponseCode::ServFail).is_retryable());
+    }
+
+    #[test]
+    fn permanent_errors_are_not_retryable() {
+        assert!(!dns_error(ResponseCode::NXDomain).is_retryable());
+        let alert = SslCheckError::TlsAlert {
+            address: address(),
+            alert: AlertDescription::HandshakeFailure,
+        };
+        assert!(!alert.is_retryable());
+        let refused = SslCheckError::ConnectionRefused { address: address() };
+        assert!(!refused.is_retryable());
+        let parse = SslCheckError::UrlParseError("x".to_string(), url::ParseError::EmptyHost);
+        assert!(!parse.is_retryable());
+    }
+
+    #[test]
+    fn tls_alert_carries_code_and_address() {
+        let alert = SslCheckError::TlsAlert {
+            address: address(),
+            alert: AlertDescription::HandshakeFailure,
+        };
+        assert_eq!(
+            alert.to_string(),
+            "TLS handshake alert from 192.0.2.10:443: handshake_failure (40)"
+        );
+        assert_eq!(alert.alert_code(), Some(40));
+        assert_eq!(alert.address(), Some(address()));
+        assert_eq!(alert.severity(), Severity::Critical);
+    }
+
+    #[test]
+    fn severity_by_error_class() {
+        let timeout = SslCheckError::Timeout {
+            stage: TimeoutStage::Overall,
+            limit: Duration::from_secs(30),
+            address: None,
+        };
+        assert_eq!(timeout.severity(), Severity::Warning);
+        assert_eq!(
+            dns_error(ResponseCode::NXDomain).severity(),
+            Severity::Error
+        );
+        let refused = SslCheckError::ConnectionRefused { address: address() };
+        assert_eq!(refused.severity(), Severity::Critical);
+    }
+}
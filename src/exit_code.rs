@@ -0,0 +1,118 @@
+use std::{fmt, str::FromStr};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::SslCheck;
+use crate::report::CheckStatus;
+
+// Process exit codes, from best to worst outcome
+pub const EXIT_OK: u8 = 0;
+pub const EXIT_WARNING: u8 = 1;
+/// Certificates that are invalid or expiring within the error threshold, and invalid
+/// configuration or arguments
+pub const EXIT_ERROR: u8 = 2;
+pub const EXIT_CHECK_FAILED: u8 = 3;
+
+// --- Least serious result that makes the process exit non-zero ---
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailOn {
+    /// Certificates expiring within the warning threshold, and anything worse
+    #[default]
+    Warning,
+    /// Invalid certificates, those expiring within the error threshold, and failed checks
+    Error,
+}
+
+impl fmt::Display for FailOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailOn::Warning => write!(f, "warning"),
+            FailOn::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+/// The exit code for a run: that of the worst result, or 0 if the worst result is
+/// less serious than `fail_on`.
+pub fn exit_code(checks: &[SslCheck], fail_on: FailOn) -> u8 {
+    let worst = checks
+        .iter()
+        .map(SslCheck::status)
+        .max()
+        .unwrap_or(CheckStatus::Ok);
+    let threshold = match fail_on {
+        FailOn::Warning => CheckStatus::Warning,
+        FailOn::Error => CheckStatus::Error,
+    };
+    if worst < threshold {
+        EXIT_OK
+    } else {
+        worst.exit_code()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DaysRemainingState;
+    use crate::errors::SslCheckError;
+    use crate::test_support::{cert, completed_check};
+
+    fn check(is_valid: bool, state: DaysRemainingState) -> SslCheck {
+        completed_check("https://example.com", cert(is_valid, 20, state))
+    }
+
+    fn failed_check() -> SslCheck {
+        crate::test_support::failed_check(
+            "https://example.com",
+            SslCheckError::InternalError("boom".to_string()),
+        )
+    }
+
+    #[test]
+    fn exit_code_reflects_the_worst_result() {
+        let ok = || check(true, DaysRemainingState::Ok);
+        let warning = || check(true, DaysRemainingState::Warning);
+        assert_eq!(exit_code(&[], FailOn::Warning), EXIT_OK);
+        assert_eq!(exit_code(&[ok()], FailOn::Warning), EXIT_OK);
+        assert_eq!(exit_code(&[ok(), warning()], FailOn::Warning), EXIT_WARNING);
+        assert_eq!(
+            exit_code(
+                &[warning(), check(false, DaysRemainingState::Ok)],
+                FailOn::Warning
+            ),
+            EXIT_ERROR
+        );
+        assert_eq!(
+            exit_code(
+                &[check(true, DaysRemainingState::Error), failed_check()],
+                FailOn::Warning
+            ),
+            EXIT_CHECK_FAILED
+        );
+    }
+
+    #[test]
+    fn fail_on_error_ignores_warnings() {
+        let warning = check(true, DaysRemainingState::Warning);
+        assert_eq!(exit_code(&[warning], FailOn::Error), EXIT_OK);
+        assert_eq!(
+            exit_code(&[check(true, DaysRemainingState::Error)], FailOn::Error),
+            EXIT_ERROR
+        );
+        assert_eq!(
+            exit_code(&[failed_check()], FailOn::Error),
+            EXIT_CHECK_FAILED
+        );
+    }
+}
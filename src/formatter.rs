@@ -1,4 +1,4 @@
-use crate::{CertCheckResult, DaysRemainingState, SslCheck};
+use crate::{CertCheckResult, DaysRemainingState, Finding, SslCheck};
 use std::fmt::{Display, Formatter};
 
 pub const PURPLE_TICK: char = '\u{2714}';
@@ -21,6 +21,19 @@ impl Display for DaysRemainingState {
     }
 }
 
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Finding::NotYetValid => "not_yet_valid",
+            Finding::Expired => "expired",
+            Finding::SelfSigned => "self_signed",
+            Finding::HostnameMismatch => "hostname_mismatch",
+            Finding::UntrustedChain => "untrusted_chain",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl Display for CertCheckResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         // Add Emojis
@@ -41,7 +54,19 @@ impl Display for SslCheck {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let check_result = match &self.result {
             Ok(check) => format!("Completed:{0} Result: {1}", PURPLE_TICK, check),
-            Err(err) => format!("Error:{0} Message: {1}", RED_CROSS, err),
+            Err(err) if self.attempts > 1 => format!(
+                "Error:{0} Severity: {1} Message: {2} (after {3} attempts)",
+                RED_CROSS,
+                err.severity(),
+                err,
+                self.attempts
+            ),
+            Err(err) => format!(
+                "Error:{0} Severity: {1} Message: {2}",
+                RED_CROSS,
+                err.severity(),
+                err
+            ),
         };
 
         write!(f, "URL: {0} {1}", self.url, check_result)
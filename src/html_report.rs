@@ -0,0 +1,378 @@
+use std::io::{self, Write};
+
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+
+use crate::DaysRemainingState;
+use crate::output::{describe_problems, format_date, format_time};
+use crate::report::{CheckRecord, CheckStatus, Report};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+// Everything the page needs is inline so it can be published as a single file
+const STYLE: &str = r##"
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #1f2328; }
+h1 { margin-bottom: 0.2rem; }
+.meta { color: #59636e; margin-top: 0; }
+.summary { display: flex; gap: 0.8rem; padding: 0; list-style: none; }
+.summary li { padding: 0.5rem 1rem; border-radius: 6px; }
+.summary b { font-size: 1.4rem; display: block; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #d1d9e0; vertical-align: top; }
+th { cursor: pointer; user-select: none; background: #f6f8fa; }
+th[aria-sort="ascending"]::after { content: " \25B2"; }
+th[aria-sort="descending"]::after { content: " \25BC"; }
+.badge { padding: 0.1rem 0.5rem; border-radius: 1rem; font-size: 0.85rem; }
+.status-ok, .state-ok { background: #dafbe1; }
+.status-warning, .state-warning { background: #fff8c5; }
+.status-error, .state-error { background: #ffebe9; }
+.status-failed { background: #e6e6e6; }
+details dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.2rem 1rem; }
+details dt { font-weight: 600; }
+details ol { padding-left: 1.2rem; }
+.timeline-row { display: grid; grid-template-columns: 20rem 1fr 12rem; gap: 0.8rem; align-items: center; margin: 0.2rem 0; }
+.timeline-label { overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+.timeline-track { position: relative; height: 1rem; background: #f6f8fa; }
+.timeline-bar { height: 100%; }
+.timeline-bar.state-ok { background: #4ac26b; }
+.timeline-bar.state-warning { background: #d4a72c; }
+.timeline-bar.state-error { background: #e5534b; }
+.threshold { position: absolute; top: -0.2rem; bottom: -0.2rem; border-left: 2px dashed #59636e; }
+"##;
+
+const SCRIPT: &str = r##"
+document.querySelectorAll("#targets th").forEach((header, column) => {
+  header.addEventListener("click", () => {
+    const table = header.closest("table");
+    const body = table.tBodies[0];
+    const ascending = header.getAttribute("aria-sort") !== "ascending";
+    table.querySelectorAll("th").forEach(th => th.removeAttribute("aria-sort"));
+    header.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+    const numeric = header.dataset.type === "number";
+    const key = row => {
+      const cell = row.cells[column];
+      const value = cell.dataset.sort ?? cell.textContent.trim();
+      return numeric ? (value === "" ? Infinity : Number(value)) : value;
+    };
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const order = numeric ? x - y : String(x).localeCompare(String(y));
+      return ascending ? order : -order;
+    });
+    rows.forEach(row => body.appendChild(row));
+  });
+});
+"##;
+
+/// Writes a self-contained HTML page with a summary, an expiry timeline and a
+/// sortable table of the results, with expandable certificate and chain details.
+pub fn write_html(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, r#"<html lang="en">"#)?;
+    writeln!(out, "<head>")?;
+    writeln!(out, r#"<meta charset="utf-8">"#)?;
+    writeln!(
+        out,
+        r#"<meta name="viewport" content="width=device-width, initial-scale=1">"#
+    )?;
+    writeln!(out, "<title>SSL Certificate Report</title>")?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>SSL Certificate Report</h1>")?;
+    write_meta(report, out)?;
+    write_summary(report, out)?;
+    write_timeline(report, out)?;
+    write_table(report, out)?;
+    writeln!(out, "<script>{}</script>", SCRIPT)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn write_meta(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let mut meta = format!("Generated {}", format_time(report.generated_at));
+    if let Some(run) = &report.run {
+        meta.push_str(&format!(
+            " by ssl_checker {} - warning below {} days, error below {} days",
+            run.tool_version,
+            run.warning_threshold_secs / SECONDS_PER_DAY,
+            run.error_threshold_secs / SECONDS_PER_DAY
+        ));
+    }
+    writeln!(out, r#"<p class="meta">{}</p>"#, esc(&meta))
+}
+
+fn write_summary(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let summary = &report.summary;
+    writeln!(out, r#"<ul class="summary">"#)?;
+    for (status, count) in [
+        (CheckStatus::Ok, summary.ok),
+        (CheckStatus::Warning, summary.warning),
+        (CheckStatus::Error, summary.error),
+        (CheckStatus::Failed, summary.failed),
+    ] {
+        writeln!(
+            out,
+            r#"<li class="status-{0}"><b>{1}</b>{0}</li>"#,
+            status, count
+        )?;
+    }
+    writeln!(out, "</ul>")
+}
+
+// A bar per certificate, soonest expiry first, scaled to the longest time remaining
+fn write_timeline(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let mut expiring: Vec<(&CheckRecord, DateTime<Utc>, i64, &DaysRemainingState)> = report
+        .results
+        .iter()
+        .filter_map(|record| {
+            let cert = record.certificate.as_ref()?;
+            Some((
+                record,
+                cert.not_after?,
+                cert.days_remaining,
+                &cert.days_remaining_state,
+            ))
+        })
+        .collect();
+    if expiring.is_empty() {
+        return Ok(());
+    }
+    expiring.sort_by_key(|(_, not_after, _, _)| *not_after);
+
+    let thresholds = report.run.as_ref().map(|run| {
+        [
+            run.warning_threshold_secs / SECONDS_PER_DAY,
+            run.error_threshold_secs / SECONDS_PER_DAY,
+        ]
+    });
+    let longest = expiring
+        .iter()
+        .map(|(_, _, days, _)| *days)
+        .chain(thresholds.iter().flatten().map(|days| *days as i64))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let position = |days: i64| (days.max(0) as f64 / longest * 100.0).min(100.0);
+
+    writeln!(out, "<h2>Expiry timeline</h2>")?;
+    writeln!(out, r#"<div class="timeline">"#)?;
+    for (record, not_after, days, state) in expiring {
+        writeln!(out, r#"<div class="timeline-row">"#)?;
+        writeln!(
+            out,
+            r#"<span class="timeline-label" title="{0}">{0}</span>"#,
+            esc(&record.url)
+        )?;
+        write!(out, r#"<div class="timeline-track">"#)?;
+        write!(
+            out,
+            r#"<div class="timeline-bar {}" style="width: {:.1}%"></div>"#,
+            state_class(state),
+            position(days)
+        )?;
+        for threshold in thresholds.iter().flatten() {
+            write!(
+                out,
+                r#"<div class="threshold" style="left: {:.1}%" title="{} days"></div>"#,
+                position(*threshold as i64),
+                threshold
+            )?;
+        }
+        writeln!(out, "</div>")?;
+        writeln!(
+            out,
+            "<span>{} ({} days)</span>",
+            format_date(not_after),
+            days
+        )?;
+        writeln!(out, "</div>")?;
+    }
+    writeln!(out, "</div>")
+}
+
+fn write_table(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "<h2>Targets</h2>")?;
+    writeln!(out, r#"<table id="targets">"#)?;
+    writeln!(
+        out,
+        r#"<thead><tr><th>Target</th><th>Status</th><th>Issuer</th><th>Expires</th><th data-type="number">Days remaining</th><th>Findings</th><th>Details</th></tr></thead>"#
+    )?;
+    writeln!(out, "<tbody>")?;
+    for record in &report.results {
+        let cert = record.certificate.as_ref();
+        writeln!(out, "<tr>")?;
+        writeln!(out, "<td>{}</td>", esc(&record.url))?;
+        writeln!(
+            out,
+            r#"<td data-sort="{}"><span class="badge status-{}">{}</span></td>"#,
+            status_rank(record.status),
+            record.status,
+            record.status
+        )?;
+        writeln!(
+            out,
+            "<td>{}</td>",
+            esc(cert.map_or("", |cert| cert.issuer.as_str()))
+        )?;
+        let not_after = cert.and_then(|cert| cert.not_after);
+        writeln!(
+            out,
+            r#"<td data-sort="{}">{}</td>"#,
+            not_after.map(format_time).unwrap_or_default(),
+            not_after.map(format_date).unwrap_or_default()
+        )?;
+        match cert {
+            Some(cert) => writeln!(
+                out,
+                r#"<td class="{}">{}</td>"#,
+                state_class(&cert.days_remaining_state),
+                cert.days_remaining
+            )?,
+            None => writeln!(out, "<td></td>")?,
+        }
+        writeln!(out, "<td>{}</td>", esc(&describe_problems(record)))?;
+        writeln!(out, "<td>")?;
+        write_details(record, out)?;
+        writeln!(out, "</td>")?;
+        writeln!(out, "</tr>")?;
+    }
+    writeln!(out, "</tbody>")?;
+    writeln!(out, "</table>")
+}
+
+fn write_details(record: &CheckRecord, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "<details><summary>Show</summary>")?;
+    let mut fields = vec![
+        ("Checked at", format_time(record.checked_at)),
+        ("Attempts", record.attempts.to_string()),
+    ];
+    if let Some(cert) = &record.certificate {
+        fields.extend([
+            ("Subject", cert.subject.clone()),
+            ("Issuer", cert.issuer.clone()),
+            ("SANs", cert.sans.as_deref().unwrap_or_default().join(", ")),
+            ("Valid", cert.is_valid.to_string()),
+            (
+                "Valid from",
+                cert.not_before.map(format_time).unwrap_or_default(),
+            ),
+            (
+                "Valid until",
+                cert.not_after.map(format_time).unwrap_or_default(),
+            ),
+        ]);
+    }
+    if let Some(error) = &record.error {
+        fields.extend([
+            ("Error", error.kind.to_string()),
+            ("Severity", error.severity.to_string()),
+            ("Message", error.message.clone()),
+            (
+                "Address",
+                error.address.map(|a| a.to_string()).unwrap_or_default(),
+            ),
+        ]);
+    }
+    writeln!(out, "<dl>")?;
+    for (name, value) in fields {
+        writeln!(out, "<dt>{}</dt><dd>{}</dd>", name, esc(&value))?;
+    }
+    writeln!(out, "</dl>")?;
+
+    if let Some(cert) = record.certificate.as_ref().filter(|c| !c.chain.is_empty()) {
+        writeln!(out, "<b>Chain</b>")?;
+        writeln!(out, "<ol>")?;
+        for link in &cert.chain {
+            writeln!(
+                out,
+                "<li><div>{}</div><small>Issued by {} - serial {} - valid {} to {}</small></li>",
+                esc(&link.subject),
+                esc(&link.issuer),
+                esc(&link.serial),
+                link.not_before.map(format_date).unwrap_or_default(),
+                link.not_after.map(format_date).unwrap_or_default()
+            )?;
+        }
+        writeln!(out, "</ol>")?;
+    }
+    writeln!(out, "</details>")
+}
+
+// Sorting the status column ascending puts the worst results first
+fn status_rank(status: CheckStatus) -> u8 {
+    match status {
+        CheckStatus::Failed => 0,
+        CheckStatus::Error => 1,
+        CheckStatus::Warning => 2,
+        CheckStatus::Ok => 3,
+    }
+}
+
+fn state_class(state: &DaysRemainingState) -> &'static str {
+    match state {
+        DaysRemainingState::Ok => "state-ok",
+        DaysRemainingState::Warning => "state-warning",
+        DaysRemainingState::Error => "state-error",
+    }
+}
+
+fn esc(text: &str) -> String {
+    escape(text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SslCheckError;
+    use crate::test_support::{cert, completed_check, failed_check};
+    use crate::{ChainCertificate, Finding};
+
+    fn report() -> Report {
+        let mut cert = cert(false, 20, DaysRemainingState::Warning);
+        cert.findings = vec![Finding::HostnameMismatch];
+        cert.not_after = Some("2025-06-21T23:59:59Z".parse().unwrap());
+        cert.chain = vec![ChainCertificate {
+            subject: "CN=example.com".to_string(),
+            issuer: "C=US, O=Let's Encrypt, CN=R11".to_string(),
+            serial: "04:d2".to_string(),
+            not_before: None,
+            not_after: cert.not_after,
+        }];
+        let mut down = failed_check(
+            "https://down.example.com",
+            SslCheckError::NoCertificatesFound("https://down.example.com".to_string()),
+        );
+        down.attempts = 2;
+        let checks = [completed_check("https://example.com/?a=<b>", cert), down];
+        Report::new(&checks)
+    }
+
+    fn html() -> String {
+        let mut out = Vec::new();
+        write_html(&report(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn html_has_summary_timeline_and_table() {
+        let html = html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(r#"<li class="status-error"><b>1</b>error</li>"#));
+        assert!(html.contains(r#"<li class="status-failed"><b>1</b>failed</li>"#));
+        assert!(html.contains("<h2>Expiry timeline</h2>"));
+        assert!(html.contains(r#"<div class="timeline-bar state-warning" style="width: 100.0%">"#));
+        assert!(html.contains(r#"<td class="state-warning">20</td>"#));
+        assert!(html.contains("<td>hostname_mismatch, expires in 20 days</td>"));
+        assert!(html.contains("Issued by C=US, O=Let&apos;s Encrypt, CN=R11"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn html_escapes_text_and_is_self_contained() {
+        let html = html();
+        assert!(html.contains("<td>https://example.com/?a=&lt;b&gt;</td>"));
+        assert!(!html.contains("<link"));
+        assert!(!html.contains(" src="));
+    }
+}
@@ -1,26 +1,54 @@
 pub mod certs;
+pub mod checker;
 pub mod config;
+pub mod duration;
 pub mod errors;
+pub mod exit_code;
 pub mod formatter;
+pub mod html_report;
+pub mod metrics;
+pub mod nagios;
+pub mod output;
+pub mod probe;
+pub mod reload;
+pub mod report;
+pub mod retry;
+pub mod schedule;
+pub mod scheduler;
+pub mod secret;
 pub mod slack_webhook;
+pub mod targets;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod validate;
 
 use crate::certs::{extract_issuer, extract_subject_common_name, is_self_signed, valid_name};
 use crate::errors::SslCheckError;
 use crate::slack_webhook::send_check_results;
 use crate::{certs::extract_sans, config::AppConfig};
 
-use reqwest::tls::TlsInfo;
-use tokio::task;
+use crate::checker::Checker;
+use crate::report::CheckStatus;
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
 use url::Url;
-use x509_parser::prelude::{FromDer, X509Certificate};
+use x509_parser::prelude::X509Certificate;
 
+/// Serializes as a [`CheckRecord`](crate::report::CheckRecord).
 #[derive(Debug)]
 pub struct SslCheck {
     pub url: String,
     pub result: Result<CertCheckResult, SslCheckError>,
+    /// Number of attempts made, including retries
+    pub attempts: u32,
+    /// When the check finished
+    pub checked_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CertCheckResult {
     pub issuer: String,
     pub subject: String,
@@ -28,9 +56,61 @@ pub struct CertCheckResult {
     pub is_valid: bool,
     pub days_remaining: i64,
     pub days_remaining_state: DaysRemainingState,
+    #[serde(default)]
+    pub not_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub not_after: Option<DateTime<Utc>>,
+    /// Why the certificate isn't valid; empty when it is
+    #[serde(default)]
+    pub findings: Vec<Finding>,
+    /// The certificates presented by the server, starting with this one
+    #[serde(default)]
+    pub chain: Vec<ChainCertificate>,
+    /// Time taken by the TLS handshake, in milliseconds
+    #[serde(default)]
+    pub handshake_duration_ms: Option<u64>,
+}
+
+// --- A certificate in the chain presented by a server ---
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainCertificate {
+    /// Distinguished name of the subject
+    pub subject: String,
+    /// Distinguished name of the issuer
+    pub issuer: String,
+    pub serial: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl ChainCertificate {
+    pub fn from_x509_certificate(cert: &X509Certificate) -> Self {
+        let validity = cert.validity();
+        Self {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string(),
+            not_before: DateTime::from_timestamp(validity.not_before.timestamp(), 0),
+            not_after: DateTime::from_timestamp(validity.not_after.timestamp(), 0),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+// --- A problem that makes a certificate invalid ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Finding {
+    NotYetValid,
+    Expired,
+    SelfSigned,
+    /// Neither the subject nor the SANs match the host in the URL
+    HostnameMismatch,
+    /// The chain doesn't lead to a trusted root; only checked when trust roots are set
+    UntrustedChain,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DaysRemainingState {
     Ok,
     Warning,
@@ -52,18 +132,22 @@ impl CertCheckResult {
             is_valid,
             days_remaining,
             days_remaining_state,
+            not_before: None,
+            not_after: None,
+            findings: Vec::new(),
+            chain: Vec::new(),
+            handshake_duration_ms: None,
         }
     }
 
     pub fn from_x509_certificate(
         certificate_url: Url,
-        warning_days: i64,
-        error_days: i64,
+        warning_threshold: Duration,
+        error_threshold: Duration,
         cert: X509Certificate,
     ) -> Self {
-        // Get Validity from cert decode - We are then going to mark it false
-        // if we can't match the CN or SANS to the URL.
-        let mut is_valid = cert.validity().is_valid();
+        // Each problem found with the certificate makes it invalid
+        let mut findings = Vec::new();
 
         let issuer = extract_issuer(&cert);
         let sans = extract_sans(&cert);
@@ -71,153 +155,117 @@ impl CertCheckResult {
         let subject = extract_subject_common_name(&cert);
         let time_to_expiry = cert.validity().time_to_expiration();
 
+        if !cert.validity().is_valid() {
+            // Past the end of the validity period there is no time left to expiry
+            findings.push(match time_to_expiry {
+                Some(_) => Finding::NotYetValid,
+                None => Finding::Expired,
+            });
+        }
+
         let days_remaining = match time_to_expiry {
             Some(dur) => dur.whole_days(),
             None => 0_i64,
         };
+        let time_remaining = time_to_expiry
+            .map(|dur| Duration::from_secs(dur.whole_seconds().max(0) as u64))
+            .unwrap_or(Duration::ZERO);
 
         if is_self_signed(&cert) {
-            is_valid = false;
+            findings.push(Finding::SelfSigned);
         };
 
         // Validate URL is in subject or sans
         if let Some(name) = certificate_url.domain() {
             if !valid_name(&cert, name) {
-                is_valid = false;
+                findings.push(Finding::HostnameMismatch);
             }
         } else {
             tracing::error!(
                 url = certificate_url.to_string(),
                 "Unable to determine domamin from url"
             );
-            is_valid = false;
+            findings.push(Finding::HostnameMismatch);
         }
         let days_remaining_state: DaysRemainingState;
 
-        if days_remaining < error_days {
+        if time_remaining < error_threshold {
             days_remaining_state = DaysRemainingState::Error;
-        } else if days_remaining < warning_days {
+        } else if time_remaining < warning_threshold {
             days_remaining_state = DaysRemainingState::Warning;
         } else {
             days_remaining_state = DaysRemainingState::Ok;
         };
 
+        let validity = cert.validity();
         Self {
             issuer,
             subject,
             sans,
-            is_valid,
+            is_valid: findings.is_empty(),
             days_remaining,
             days_remaining_state,
+            not_before: DateTime::from_timestamp(validity.not_before.timestamp(), 0),
+            not_after: DateTime::from_timestamp(validity.not_after.timestamp(), 0),
+            findings,
+            chain: Vec::new(),
+            handshake_duration_ms: None,
         }
     }
 }
 
-pub async fn run(app_config: &AppConfig) -> Result<Vec<SslCheck>, Box<dyn std::error::Error>> {
-    if let Some(webhook_url) = &app_config.slack_webhook_url {
-        tracing::info!(slack_webhook_url = %webhook_url, "Slack notifications enabled.");
-    } else {
-        tracing::info!("Slack notifications disabled.");
+impl SslCheck {
+    /// The overall outcome of the check, taking both validity and expiry into account.
+    pub fn status(&self) -> CheckStatus {
+        match &self.result {
+            Err(_) => CheckStatus::Failed,
+            Ok(cert) if !cert.is_valid => CheckStatus::Error,
+            Ok(cert) => match cert.days_remaining_state {
+                DaysRemainingState::Ok => CheckStatus::Ok,
+                DaysRemainingState::Warning => CheckStatus::Warning,
+                DaysRemainingState::Error => CheckStatus::Error,
+            },
+        }
     }
+}
+
+/// Checks every configured target, returning the results in the order the targets
+/// were configured. Notifications are not sent; see [`notify`].
+pub async fn run(app_config: &AppConfig) -> Result<Vec<SslCheck>, Box<dyn std::error::Error>> {
+    run_targets(app_config, &app_config.urls).await
+}
 
+/// Checks the given subset of the configured targets, such as those that are due
+/// in daemon mode.
+pub async fn run_targets(
+    app_config: &AppConfig,
+    urls: &[String],
+) -> Result<Vec<SslCheck>, Box<dyn std::error::Error>> {
+    let checker = Checker::from_config(app_config)?;
+    Ok(check_targets(&checker, urls).await)
+}
+
+/// Checks `urls` with an existing checker, returning the results in the same order.
+pub async fn check_targets(checker: &Checker, urls: &[String]) -> Vec<SslCheck> {
     tracing::info!("Starting SSL certificate checks...");
 
-    let client = reqwest::Client::builder()
-        .danger_accept_invalid_certs(true) // We want bad certs so we can report on them
-        .use_rustls_tls() // Explicitly use rustls
-        .tls_info(true) // Make sure we expose the tls cert
-        .build()?;
-
-    let warning_days = app_config.warning_days;
-    let error_days = app_config.error_days;
-
-    let handles: Vec<_> = app_config
-        .urls
-        .clone()
-        .into_iter()
-        .map(|url| {
-            let client = client.clone();
-            task::spawn(async move {
-                get_ssl_certificate(&client, &url, warning_days, error_days).await
-            })
-        })
-        .collect();
+    let mut check_results: Vec<SslCheck> = checker.check_many(urls.to_vec()).collect().await;
 
-    let check_results: Vec<SslCheck> = futures::future::join_all(handles)
-        .await
-        .into_iter()
-        .filter_map(|res| match res {
-            Ok(cert_result) => Some(cert_result),
-            Err(e) => {
-                tracing::error!(error = %e, "Failed to properly process URL");
-                None
-            }
-        })
+    // Results arrive as they finish, so put them back in target order
+    let positions: HashMap<&str, usize> = urls
+        .iter()
+        .enumerate()
+        .map(|(idx, url)| (url.as_str(), idx))
         .collect();
+    check_results.sort_by_key(|check| positions.get(check.url.as_str()).copied());
 
-    // Send Slack Notifications
-    if let Some(webhook_url) = &app_config.slack_webhook_url {
-        tracing::info!("Sending Slack notifications...");
-        send_check_results(webhook_url, &check_results).await;
-    }
-
-    Ok(check_results)
+    check_results
 }
 
-async fn get_ssl_certificate(
-    client: &reqwest::Client,
-    url_str: &str,
-    warning_days: i64,
-    error_days: i64,
-) -> SslCheck {
-    let parse_result = reqwest::Url::parse(url_str);
-
-    let parsed_url = match parse_result {
-        Ok(url) => url,
-        Err(e) => {
-            return SslCheck {
-                url: url_str.to_string(),
-                result: Err(SslCheckError::UrlParseError(url_str.to_string(), e)),
-            };
-        }
-    };
-
-    tracing::debug!(url = url_str, "Attempting to retrieve SSL certificate");
-    let response = client.head(parsed_url.clone()).send().await;
-
-    if response.is_err() {
-        tracing::error!(url = url_str, "Failed to retrieve SSL certificate");
-        return SslCheck {
-            url: url_str.to_string(),
-            result: Err(SslCheckError::NetworkError(response.unwrap_err())),
-        };
-    };
-    let response = response.unwrap();
-
-    // Access the DER encoded certificate from  TLS info
-    if let Some(tls_info) = response.extensions().get::<TlsInfo>() {
-        if let Some(cert_der) = tls_info.peer_certificate() {
-            if let Ok((_, cert)) = X509Certificate::from_der(cert_der) {
-                let cert_result = CertCheckResult::from_x509_certificate(
-                    parsed_url,
-                    warning_days,
-                    error_days,
-                    cert,
-                );
-
-                return SslCheck {
-                    url: url_str.to_string(),
-                    result: Ok(cert_result),
-                };
-            } else {
-                tracing::warn!("No Cert Detail Found");
-            }
-        } else {
-            tracing::warn!("No TLS Info Found");
-        }
-    }
-    SslCheck {
-        url: url_str.to_string(),
-        result: Err(SslCheckError::NoCertificatesFound(url_str.to_string())),
+/// Sends the results to the notifiers enabled in the configuration.
+pub async fn notify(app_config: &AppConfig, check_results: &[SslCheck]) {
+    if let Some(webhook_url) = &app_config.slack_webhook_url {
+        tracing::info!("Sending Slack notifications...");
+        send_check_results(webhook_url.expose(), check_results).await;
     }
 }
@@ -1,63 +1,416 @@
-use clap::Parser; // Needed to use CliArgs:parse
-use ssl_checker::config::{AppConfig, CliArgs};
-use ssl_checker::run;
+use chrono::{DateTime, Utc};
+use clap::Parser; // Needed to use Cli:parse
+use ssl_checker::checker::Checker;
+use ssl_checker::config::{AppConfig, Cli, CliArgs, Command, Mode, SecretHandling};
+use ssl_checker::duration::HumanDuration;
+use ssl_checker::exit_code::{EXIT_ERROR, exit_code};
+use ssl_checker::metrics::{MetricsRegistry, serve_metrics};
+use ssl_checker::nagios::{PluginState, plugin_state, write_nagios};
+use ssl_checker::output::{OutputFormat, write_output};
+use ssl_checker::reload::ConfigReloader;
+use ssl_checker::report::RunMetadata;
+use ssl_checker::schedule::Schedule;
+use ssl_checker::scheduler::Scheduler;
+use ssl_checker::slack_webhook::send_run_failure;
+use ssl_checker::validate::validate;
+use ssl_checker::{SslCheck, check_targets, notify, run};
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::signal::unix::{Signal, SignalKind, signal};
+use tokio::time::Instant;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{EnvFilter, fmt as tracing_fmt};
 
+// How often config files are checked for changes in daemon mode
+const CONFIG_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli_args = CliArgs::parse();
-    let app_config = AppConfig::build(cli_args)?;
+async fn main() -> ExitCode {
+    let result = match Cli::parse().into_command() {
+        Command::Check(cli_args) => check(cli_args).await,
+        Command::ValidateConfig(cli_args) => Ok(validate_config(cli_args)),
+    };
+    // Errors that stop the run, such as invalid configuration, share the exit code for
+    // invalid certificates rather than the default of 1, which means a warning
+    result.unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        ExitCode::from(EXIT_ERROR)
+    })
+}
+
+fn validate_config(cli_args: CliArgs) -> ExitCode {
+    match validate(cli_args, SecretHandling::CheckSyntax) {
+        Ok(app_config) => {
+            println!("Configuration is valid ({} URLs)", app_config.urls.len());
+            ExitCode::SUCCESS
+        }
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("error: {}", diagnostic);
+            }
+            eprintln!("Configuration is invalid ({} errors)", diagnostics.len());
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+async fn check(cli_args: CliArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let app_config = match AppConfig::build(cli_args.clone()) {
+        Ok(app_config) => app_config,
+        // A plugin must always answer with a status line, even when it can't run
+        Err(error) if cli_args.requested_mode() == Mode::Nagios => {
+            println!("SSL {} - {}", PluginState::Unknown, error);
+            return Ok(ExitCode::from(PluginState::Unknown.exit_code()));
+        }
+        Err(error) => return Err(error.into()),
+    };
 
     // Initialize tracing subscriber
     let env_filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new(format!("ssl_checker={}", &app_config.log_level)))
         .unwrap_or_else(|_| EnvFilter::new("ssl_checker=info")); // Fallback to default if parsing fails
 
-    tracing_fmt().with_env_filter(env_filter).init();
+    // Keep stdout clean when machine readable output is written to it
+    let output = &app_config.output;
+    let text_on_stdout = output.format == OutputFormat::Text || output.path.is_some();
+    let log_writer = if text_on_stdout && app_config.mode == Mode::Standard {
+        BoxMakeWriter::new(std::io::stdout)
+    } else {
+        BoxMakeWriter::new(std::io::stderr)
+    };
+    tracing_fmt()
+        .with_env_filter(env_filter)
+        .with_writer(log_writer)
+        .init();
 
     tracing::info!("Logger initialized");
     tracing::info!(
         urls = ?app_config.urls,
-        error_days = app_config.error_days,
-        warning_days = app_config.warning_days,
+        error_days = %HumanDuration(app_config.error_threshold),
+        warning_days = %HumanDuration(app_config.warning_threshold),
         log_level = %app_config.log_level,
+        connect_timeout = %HumanDuration(app_config.timeouts.connect),
+        handshake_timeout = %HumanDuration(app_config.timeouts.handshake),
+        timeout = %HumanDuration(app_config.timeouts.overall),
+        retries = app_config.retry_policy.retries,
+        retry_backoff = %HumanDuration(app_config.retry_policy.backoff),
+        concurrency = app_config.concurrency,
+        per_host_concurrency = app_config.per_host_concurrency,
+        output = %app_config.output.format,
+        out = ?app_config.output.path,
+        fail_on = %app_config.fail_on,
+        mode = %app_config.mode,
+        metrics_address = ?app_config.metrics_address,
         slack_webhook_url = ?app_config.slack_webhook_url,
         "Effective Configuration Loaded"
     );
+    for duplicate in &app_config.duplicate_urls {
+        tracing::warn!("{}", duplicate);
+    }
+    if app_config.slack_webhook_url.is_some() {
+        tracing::info!("Slack notifications enabled.");
+    } else {
+        tracing::info!("Slack notifications disabled.");
+    }
+
+    if app_config.mode == Mode::Nagios {
+        return Ok(check_as_plugin(&app_config).await);
+    }
 
-    if let Some(check_frequency) = app_config.check_frequency {
-        tracing::info!(
-            "Running in Daemon mode - Check will be run every {} days.",
-            check_frequency
-        );
-        let mut ticker = tokio::time::interval(tokio::time::Duration::from_secs(
-            60 * 60 * 24 * check_frequency as u64,
-        ));
+    if let Some(schedule) = &app_config.schedule {
+        tracing::info!("Running in Daemon mode - Checks will be run {}.", schedule);
+        let mut reloader = ConfigReloader::supports_reload(&app_config)
+            .then(|| ConfigReloader::new(cli_args, &app_config));
+        if reloader.is_none() {
+            tracing::warn!("URLs were read from stdin - configuration reloading is disabled");
+        }
+        let mut app_config = app_config;
+        let mut hangup = signal(SignalKind::hangup())?;
+        let mut shutdown = ShutdownSignals::new()?;
+        let mut watch_ticker = tokio::time::interval(CONFIG_WATCH_INTERVAL);
+        let mut scheduler = Scheduler::new(&app_config, Utc::now());
+        // Kept between runs so connections are set up once, and rebuilt after a reload
+        let mut checker = None;
+        // Results waiting to be sent until the jittered targets of the period have run
+        let mut unreported = Vec::new();
+        let metrics = match app_config.metrics_address {
+            Some(address) => {
+                let listener = TcpListener::bind(address).await.map_err(|error| {
+                    format!("Failed to listen for metrics on {}: {}", address, error)
+                })?;
+                tracing::info!(%address, "Serving Prometheus metrics on /metrics");
+                let metrics = MetricsRegistry::new(&app_config);
+                tokio::spawn(serve_metrics(listener, metrics.clone()));
+                Some(metrics)
+            }
+            None => None,
+        };
+        log_next_run(scheduler.next_run(), &app_config);
         loop {
-            ticker.tick().await;
-            tracing::info!("Running SSL Checks");
-            let results = run(&app_config).await;
-            tracing::info!("SSL Checks Complete");
-            match results {
-                Ok(results) => {
-                    for result in results {
-                        tracing::info!("{}", result)
+            let Some(due) = scheduler.next_run() else {
+                tracing::error!("The schedule has no further runs - stopping");
+                break;
+            };
+            let reload_requested = tokio::select! {
+                _ = tokio::time::sleep_until(instant_at(due)) => false,
+                signal = shutdown.recv() => {
+                    tracing::info!("{} received - shutting down", signal);
+                    break;
+                }
+                _ = hangup.recv() => {
+                    tracing::info!("SIGHUP received - reloading configuration");
+                    true
+                }
+                _ = watch_ticker.tick() => {
+                    let changed = reloader.as_ref().is_some_and(ConfigReloader::has_changed);
+                    if changed {
+                        tracing::info!("Configuration change detected - reloading configuration");
                     }
+                    changed
                 }
-                Err(error) => {
-                    tracing::error!(error, "Error running SSL Checks");
-                    break;
+            };
+
+            if reload_requested {
+                if let Some(new_config) = reloader.as_mut().and_then(reload_config) {
+                    if new_config.log_level != app_config.log_level {
+                        tracing::warn!("log_level changes take effect after a restart");
+                    }
+                    if new_config.metrics_address != app_config.metrics_address {
+                        tracing::warn!("metrics_address changes take effect after a restart");
+                    }
+                    if let Some(metrics) = &metrics {
+                        metrics.update_config(&new_config);
+                    }
+                    app_config = new_config;
+                    checker = None;
+                    scheduler.update(&app_config, Utc::now());
+                    log_next_run(scheduler.next_run(), &app_config);
+                    if !scheduler.has_delayed_targets(Utc::now()) {
+                        notify_unreported(&app_config, &mut unreported).await;
+                    }
                 }
+                continue;
+            }
+
+            let urls = scheduler.take_due(Utc::now());
+            if urls.is_empty() {
+                continue;
             }
+            let checks = run_checks(&app_config, &mut checker, metrics.as_ref(), &urls);
+            tokio::pin!(checks);
+            let (results, shutting_down) = tokio::select! {
+                results = &mut checks => (results, false),
+                signal = shutdown.recv() => {
+                    tracing::info!("{} received - waiting for in-flight checks to finish", signal);
+                    (checks.await, true)
+                }
+            };
+            let succeeded = results.is_some();
+            unreported.extend(results.unwrap_or_default());
+            if shutting_down {
+                break;
+            }
+
+            if !scheduler.has_delayed_targets(Utc::now()) {
+                notify_unreported(&app_config, &mut unreported).await;
+            }
+            if succeeded {
+                scheduler.record_success(&urls);
+            } else {
+                let backoff = scheduler.record_failure(&urls, Utc::now());
+                tracing::warn!(
+                    retry_in = %HumanDuration(backoff),
+                    "SSL Checks failed - retrying after backoff"
+                );
+            }
+            log_next_run(scheduler.next_run(), &app_config);
         }
+        notify_unreported(&app_config, &mut unreported).await;
+        tracing::info!("Shutdown complete");
     } else {
         tracing::info!("Running in Non-Daemon mode");
+        let started_at = Utc::now();
         let results = run(&app_config).await?;
+        notify(&app_config, &results).await;
+
+        let run_metadata = RunMetadata::new(&app_config, started_at);
+        write_output(
+            &app_config.output,
+            &app_config.target_options,
+            &results,
+            &run_metadata,
+        )?;
+        return Ok(ExitCode::from(exit_code(&results, app_config.fail_on)));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
 
+// Runs the checks once and prints the results as a Nagios plugin. A run that fails
+// is reported as unknown.
+async fn check_as_plugin(app_config: &AppConfig) -> ExitCode {
+    if app_config.schedule.is_some() {
+        tracing::info!("Running in Nagios mode - the schedule is ignored");
+    }
+    let results = match run(app_config).await {
+        Ok(results) => results,
+        Err(error) => {
+            println!("SSL {} - {}", PluginState::Unknown, error);
+            return ExitCode::from(PluginState::Unknown.exit_code());
+        }
+    };
+    notify(app_config, &results).await;
+
+    let mut stdout = std::io::stdout().lock();
+    let written = write_nagios(
+        &results,
+        app_config.warning_threshold,
+        app_config.error_threshold,
+        &mut stdout,
+    );
+    if let Err(error) = written {
+        tracing::error!(%error, "Failed to write results");
+        return ExitCode::from(PluginState::Unknown.exit_code());
+    }
+    ExitCode::from(plugin_state(&results).exit_code())
+}
+
+// Runs one round of checks, logging the results and recording them for the metrics
+// endpoint, and returns them to be notified. The checker is created on the first run
+// and reused after that. A run that fails is logged and reported to Slack, and None is
+// returned so the daemon can back off and retry.
+async fn run_checks(
+    app_config: &AppConfig,
+    checker: &mut Option<Checker>,
+    metrics: Option<&MetricsRegistry>,
+    urls: &[String],
+) -> Option<Vec<SslCheck>> {
+    tracing::info!(targets = urls.len(), "Running SSL Checks");
+    let checker = match checker {
+        Some(checker) => Ok(checker),
+        None => Checker::from_config(app_config).map(|new_checker| checker.insert(new_checker)),
+    };
+    let started_at = Utc::now();
+    let error = match checker {
+        Ok(checker) => {
+            let results = check_targets(checker, urls).await;
+            tracing::info!("SSL Checks Complete");
+            if let Some(metrics) = metrics {
+                metrics.record(&results);
+            }
+            log_results(app_config, &results, started_at);
+            return Some(results);
+        }
+        Err(error) => format!("Failed to create the TLS client: {}", error),
+    };
+
+    tracing::error!(error, "Error running SSL Checks");
+    if let Some(webhook_url) = &app_config.slack_webhook_url {
+        send_run_failure(webhook_url.expose(), &error).await;
+    }
+    None
+}
+
+// Sends the results held back since the last notification, if there are any
+async fn notify_unreported(app_config: &AppConfig, unreported: &mut Vec<SslCheck>) {
+    if !unreported.is_empty() {
+        notify(app_config, &std::mem::take(unreported)).await;
+    }
+}
+
+// Text results are logged unless they go to a file; other formats are written out
+// after each run
+fn log_results(app_config: &AppConfig, results: &[SslCheck], started_at: DateTime<Utc>) {
+    let output = &app_config.output;
+    if output.format == OutputFormat::Text && output.path.is_none() {
         for result in results {
-            println!("{}", result)
+            tracing::info!("{}", result)
         }
+        return;
+    }
+    let run_metadata = RunMetadata::new(app_config, started_at);
+    if let Err(error) = write_output(output, &app_config.target_options, results, &run_metadata) {
+        tracing::error!(%error, "Failed to write results");
     }
+}
 
-    Ok(())
+// SIGTERM and SIGINT both ask the daemon to stop once in-flight work has finished
+struct ShutdownSignals {
+    terminate: Signal,
+    interrupt: Signal,
+}
+
+impl ShutdownSignals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    // Returns the name of the signal that was received
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.terminate.recv() => "SIGTERM",
+            _ = self.interrupt.recv() => "SIGINT",
+        }
+    }
+}
+
+// Returns the new configuration, or None if the current one should stay in use
+fn reload_config(reloader: &mut ConfigReloader) -> Option<AppConfig> {
+    match reloader.reload() {
+        Ok(new_config) if new_config.schedule.is_none() => {
+            tracing::error!(
+                "Rejected new configuration: the schedule cannot be removed while running in Daemon mode"
+            );
+            None
+        }
+        Ok(new_config) => {
+            tracing::info!(
+                urls = ?new_config.urls,
+                error_days = %HumanDuration(new_config.error_threshold),
+                warning_days = %HumanDuration(new_config.warning_threshold),
+                schedule = ?new_config.schedule.as_ref().map(ToString::to_string),
+                "Configuration reloaded"
+            );
+            for duplicate in &new_config.duplicate_urls {
+                tracing::warn!("{}", duplicate);
+            }
+            Some(new_config)
+        }
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                tracing::error!("{}", diagnostic);
+            }
+            tracing::error!("Rejected new configuration - keeping the last good configuration");
+            None
+        }
+    }
+}
+
+// Converts a wall-clock time into a tokio deadline; times in the past are due now
+fn instant_at(time: DateTime<Utc>) -> Instant {
+    let delay = (time - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+    Instant::now() + delay
+}
+
+// Uses the time zone of the current configuration, which a reload can change
+fn log_next_run(next_run: Option<DateTime<Utc>>, config: &AppConfig) {
+    match (
+        next_run,
+        config.schedule.as_ref().and_then(Schedule::timezone),
+    ) {
+        (Some(next_run), Some(timezone)) => tracing::info!(
+            next_run = %next_run.to_rfc3339(),
+            local_time = %next_run.with_timezone(&timezone).to_rfc3339(),
+            timezone = %timezone,
+            "Next check scheduled"
+        ),
+        (Some(next_run), None) => {
+            tracing::info!(next_run = %next_run.to_rfc3339(), "Next check scheduled")
+        }
+        (None, _) => {}
+    }
 }
@@ -0,0 +1,324 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, header};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+
+use crate::SslCheck;
+use crate::config::AppConfig;
+use crate::report::CheckRecord;
+use crate::targets::TargetOptions;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// --- A gauge reported for each target ---
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    /// The value for a target, if the metric applies to its result
+    value: fn(&CheckRecord) -> Option<f64>,
+}
+
+const FAMILIES: [Family; 6] = [
+    Family {
+        name: "ssl_checker_certificate_expiry_timestamp_seconds",
+        help: "Time the certificate expires, as a Unix timestamp",
+        value: |record| {
+            let not_after = record.certificate.as_ref()?.not_after?;
+            Some(not_after.timestamp() as f64)
+        },
+    },
+    Family {
+        name: "ssl_checker_certificate_days_remaining",
+        help: "Whole days until the certificate expires",
+        value: |record| Some(record.certificate.as_ref()?.days_remaining as f64),
+    },
+    Family {
+        name: "ssl_checker_certificate_valid",
+        help: "Whether the certificate is valid (1) or has findings (0)",
+        value: |record| Some(bool_value(record.certificate.as_ref()?.is_valid)),
+    },
+    Family {
+        name: "ssl_checker_check_success",
+        help: "Whether the last check completed (1) or failed (0)",
+        value: |record| Some(bool_value(record.error.is_none())),
+    },
+    Family {
+        name: "ssl_checker_handshake_duration_seconds",
+        help: "Time taken by the TLS handshake in the last check",
+        value: |record| {
+            let millis = record.certificate.as_ref()?.handshake_duration_ms?;
+            Some(millis as f64 / 1000.0)
+        },
+    },
+    Family {
+        name: "ssl_checker_last_check_timestamp_seconds",
+        help: "Time the last check finished, as a Unix timestamp",
+        value: |record| Some(record.checked_at.timestamp_millis() as f64 / 1000.0),
+    },
+];
+
+fn bool_value(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+/// Writes the results as gauges in the Prometheus text exposition format. Each
+/// sample is labelled with its target URL and the tags in the target's options.
+pub fn write_prometheus(
+    records: &[CheckRecord],
+    target_options: &HashMap<String, TargetOptions>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let labels: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let tags = target_options.get(&record.url).map(|options| &options.tags);
+            format_labels(&record.url, tags)
+        })
+        .collect();
+    for family in &FAMILIES {
+        writeln!(out, "# HELP {} {}", family.name, family.help)?;
+        writeln!(out, "# TYPE {} gauge", family.name)?;
+        for (record, labels) in records.iter().zip(&labels) {
+            if let Some(value) = (family.value)(record) {
+                writeln!(out, "{}{{{}}} {}", family.name, labels, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The target label followed by the tags, which can't replace it
+fn format_labels(url: &str, tags: Option<&BTreeMap<String, String>>) -> String {
+    let mut labels = vec![format!("target=\"{}\"", escape_label_value(url))];
+    for (name, value) in tags.into_iter().flatten() {
+        labels.push(format!("{}=\"{}\"", name, escape_label_value(value)));
+    }
+    labels.join(",")
+}
+
+/// Whether `name` can be used as a Prometheus label name as it is.
+pub fn is_valid_label_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with("__")
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// --- Latest result for each target, served as metrics in daemon mode ---
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    state: Arc<Mutex<RegistryState>>,
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    urls: Vec<String>,
+    target_options: HashMap<String, TargetOptions>,
+    results: HashMap<String, CheckRecord>,
+}
+
+impl MetricsRegistry {
+    pub fn new(config: &AppConfig) -> Self {
+        let registry = Self::default();
+        registry.update_config(config);
+        registry
+    }
+
+    /// Switches to the targets and tags of a new configuration, dropping the results
+    /// of targets that are no longer configured.
+    pub fn update_config(&self, config: &AppConfig) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.urls = config.urls.clone();
+        state.target_options = config.target_options.clone();
+        state
+            .results
+            .retain(|url, _| config.urls.iter().any(|configured| configured == url));
+    }
+
+    /// Replaces the previous results of the checked targets.
+    pub fn record(&self, checks: &[SslCheck]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        for check in checks {
+            state
+                .results
+                .insert(check.url.clone(), CheckRecord::from(check));
+        }
+    }
+
+    /// The metrics for every target that has been checked, in target order.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let records: Vec<CheckRecord> = state
+            .urls
+            .iter()
+            .filter_map(|url| state.results.get(url).cloned())
+            .collect();
+        let mut out = Vec::new();
+        write_prometheus(&records, &state.target_options, &mut out)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("metrics are valid UTF-8")
+    }
+}
+
+/// Serves the registry's metrics over HTTP on `/metrics` until the task is dropped.
+pub async fn serve_metrics(listener: TcpListener, registry: MetricsRegistry) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                tracing::warn!(%error, "Failed to accept a metrics connection");
+                continue;
+            }
+        };
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|request| {
+                let response = respond(&registry, &request);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            if let Err(error) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!(%error, "Metrics connection closed with an error");
+            }
+        });
+    }
+}
+
+fn respond(registry: &MetricsRegistry, request: &Request<Incoming>) -> Response<Full<Bytes>> {
+    let (status, content_type, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => (StatusCode::OK, CONTENT_TYPE, registry.render()),
+        (_, "/metrics") => (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "text/plain",
+            "Method Not Allowed\n".to_string(),
+        ),
+        _ => (
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "Not Found\n".to_string(),
+        ),
+    };
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Full::new(Bytes::from(body)))
+        .expect("response parts are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DaysRemainingState;
+    use crate::errors::SslCheckError;
+    use crate::test_support::{self, cert};
+
+    fn completed_check(url: &str) -> SslCheck {
+        let mut cert = cert(true, 20, DaysRemainingState::Warning);
+        cert.not_after = Some("2025-06-21T23:59:59Z".parse().unwrap());
+        cert.handshake_duration_ms = Some(42);
+        let mut check = test_support::completed_check(url, cert);
+        check.checked_at = "2025-06-01T12:00:00Z".parse().unwrap();
+        check
+    }
+
+    fn failed_check(url: &str) -> SslCheck {
+        let error = SslCheckError::InternalError("boom".to_string());
+        let mut check = test_support::failed_check(url, error);
+        check.checked_at = "2025-06-01T12:00:00.5Z".parse().unwrap();
+        check
+    }
+
+    #[test]
+    fn writes_a_gauge_per_target_with_tags() {
+        let records = [
+            CheckRecord::from(&completed_check("https://example.com")),
+            CheckRecord::from(&failed_check("https://down.example.com")),
+        ];
+        let tags = BTreeMap::from([
+            ("team".to_string(), "payments".to_string()),
+            ("cost_centre".to_string(), "a \"b\"".to_string()),
+        ]);
+        let target_options = HashMap::from([(
+            "https://example.com".to_string(),
+            TargetOptions {
+                tags,
+                ..Default::default()
+            },
+        )]);
+        let mut out = Vec::new();
+        write_prometheus(&records, &target_options, &mut out).unwrap();
+        let metrics = String::from_utf8(out).unwrap();
+
+        let labels = r#"target="https://example.com",cost_centre="a \"b\"",team="payments""#;
+        let down = r#"target="https://down.example.com""#;
+        for sample in [
+            format!("ssl_checker_certificate_expiry_timestamp_seconds{{{labels}}} 1750550399"),
+            format!("ssl_checker_certificate_days_remaining{{{labels}}} 20"),
+            format!("ssl_checker_certificate_valid{{{labels}}} 1"),
+            format!("ssl_checker_check_success{{{labels}}} 1"),
+            format!("ssl_checker_check_success{{{down}}} 0"),
+            format!("ssl_checker_handshake_duration_seconds{{{labels}}} 0.042"),
+            format!("ssl_checker_last_check_timestamp_seconds{{{down}}} 1748779200.5"),
+        ] {
+            assert!(
+                metrics.lines().any(|line| line == sample),
+                "missing {sample}"
+            );
+        }
+        assert!(metrics.contains("# TYPE ssl_checker_certificate_valid gauge\n"));
+        // Failed checks have no certificate metrics
+        assert!(!metrics.contains(&format!("ssl_checker_certificate_valid{{{down}}}")));
+    }
+
+    #[test]
+    fn registry_keeps_the_latest_result_of_configured_targets() {
+        let registry = MetricsRegistry::default();
+        let mut state = registry.state.lock().unwrap();
+        state.urls = vec!["https://a.com".to_string(), "https://b.com".to_string()];
+        drop(state);
+
+        registry.record(&[completed_check("https://b.com")]);
+        registry.record(&[
+            failed_check("https://b.com"),
+            completed_check("https://a.com"),
+        ]);
+        let metrics = registry.render();
+        assert!(metrics.contains("ssl_checker_check_success{target=\"https://b.com\"} 0\n"));
+        let a = metrics.find("{target=\"https://a.com\"}").unwrap();
+        let b = metrics.find("{target=\"https://b.com\"}").unwrap();
+        assert!(a < b, "targets should be in configured order");
+
+        registry.state.lock().unwrap().urls.truncate(1);
+        registry.record(&[]);
+        assert!(!registry.render().contains("https://b.com"));
+    }
+
+    #[test]
+    fn label_names_are_validated() {
+        assert!(is_valid_label_name("team"));
+        assert!(is_valid_label_name("cost_centre"));
+        assert!(!is_valid_label_name("cost-centre"));
+        assert!(!is_valid_label_name("__name__"));
+        assert!(!is_valid_label_name("1st"));
+        assert!(!is_valid_label_name(""));
+    }
+}
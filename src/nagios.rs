@@ -0,0 +1,233 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::SslCheck;
+use crate::report::CheckStatus;
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+// --- Service state reported by a Nagios plugin ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginState {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl PluginState {
+    /// The plugin exit code for the state
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PluginState::Ok => 0,
+            PluginState::Warning => 1,
+            PluginState::Critical => 2,
+            PluginState::Unknown => 3,
+        }
+    }
+
+    // How bad the state is when results are combined. A certificate known to be bad
+    // outranks a target that couldn't be checked, so it isn't hidden by one.
+    fn severity(&self) -> u8 {
+        match self {
+            PluginState::Ok => 0,
+            PluginState::Unknown => 1,
+            PluginState::Warning => 2,
+            PluginState::Critical => 3,
+        }
+    }
+}
+
+impl From<CheckStatus> for PluginState {
+    fn from(status: CheckStatus) -> Self {
+        match status {
+            CheckStatus::Ok => PluginState::Ok,
+            CheckStatus::Warning => PluginState::Warning,
+            CheckStatus::Error => PluginState::Critical,
+            CheckStatus::Failed => PluginState::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for PluginState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginState::Ok => write!(f, "OK"),
+            PluginState::Warning => write!(f, "WARNING"),
+            PluginState::Critical => write!(f, "CRITICAL"),
+            PluginState::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+/// The state of a run: that of the worst result, or unknown if there were no results.
+/// Critical is worst, followed by warning, unknown and ok.
+pub fn plugin_state(checks: &[SslCheck]) -> PluginState {
+    checks
+        .iter()
+        .map(|check| PluginState::from(check.status()))
+        .max_by_key(PluginState::severity)
+        .unwrap_or(PluginState::Unknown)
+}
+
+/// Writes the results in the Nagios plugin output format. A single target gets one
+/// status line; several targets get a summary line followed by a line per target
+/// (long output). Days remaining are reported as performance data, with the warning
+/// and error thresholds in whole days.
+pub fn write_nagios(
+    checks: &[SslCheck],
+    warning_threshold: Duration,
+    error_threshold: Duration,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let state = plugin_state(checks);
+    let thresholds = format!(
+        "{};{}",
+        warning_threshold.as_secs() / SECONDS_PER_DAY,
+        error_threshold.as_secs() / SECONDS_PER_DAY
+    );
+
+    let summary = match checks {
+        [] => "no targets configured".to_string(),
+        [check] => describe(check),
+        _ => summarize(checks),
+    };
+    let perf_data: Vec<String> = checks
+        .iter()
+        .filter_map(|check| {
+            let days = check.result.as_ref().ok()?.days_remaining;
+            let label = match checks.len() {
+                1 => "days".to_string(),
+                _ => format!("'{} days'", perf_label(&check.url)),
+            };
+            Some(format!("{}={};{}", label, days, thresholds))
+        })
+        .collect();
+
+    write!(out, "SSL {} - {}", state, summary)?;
+    if !perf_data.is_empty() {
+        write!(out, " | {}", perf_data.join(" "))?;
+    }
+    writeln!(out)?;
+
+    if checks.len() > 1 {
+        for check in checks {
+            let state = PluginState::from(check.status());
+            writeln!(out, "[{}] {} - {}", state, check.url, describe(check))?;
+        }
+    }
+    Ok(())
+}
+
+// The problems with a target followed by its days remaining, or the error for a
+// failed check
+fn describe(check: &SslCheck) -> String {
+    match &check.result {
+        Ok(cert) => {
+            let mut parts: Vec<String> = cert.findings.iter().map(ToString::to_string).collect();
+            let unit = if cert.days_remaining == 1 {
+                "day"
+            } else {
+                "days"
+            };
+            parts.push(format!("{} {} remaining", cert.days_remaining, unit));
+            parts.join(", ")
+        }
+        Err(error) => error.to_string(),
+    }
+}
+
+// Number of targets in each state, worst first
+fn summarize(checks: &[SslCheck]) -> String {
+    let states = [
+        PluginState::Critical,
+        PluginState::Warning,
+        PluginState::Unknown,
+        PluginState::Ok,
+    ];
+    let counts: Vec<String> = states
+        .iter()
+        .filter_map(|state| {
+            let count = checks
+                .iter()
+                .filter(|check| PluginState::from(check.status()) == *state)
+                .count();
+            (count > 0).then(|| format!("{} {}", count, state.to_string().to_lowercase()))
+        })
+        .collect();
+    format!("{} targets: {}", checks.len(), counts.join(", "))
+}
+
+// Quotes and equals signs can't appear in a performance data label
+fn perf_label(url: &str) -> String {
+    url.replace(['\'', '='], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SslCheckError;
+    use crate::test_support::{cert, completed_check, failed_check};
+    use crate::{DaysRemainingState, Finding};
+
+    fn days(days: u64) -> Duration {
+        Duration::from_secs(days * SECONDS_PER_DAY)
+    }
+
+    fn check(url: &str, days_remaining: i64, state: DaysRemainingState) -> SslCheck {
+        completed_check(url, cert(true, days_remaining, state))
+    }
+
+    fn render(checks: &[SslCheck]) -> String {
+        let mut out = Vec::new();
+        write_nagios(checks, days(30), days(14), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn single_target_has_one_status_line() {
+        let checks = [check("https://example.com", 45, DaysRemainingState::Ok)];
+        assert_eq!(
+            render(&checks),
+            "SSL OK - 45 days remaining | days=45;30;14\n"
+        );
+        assert_eq!(plugin_state(&checks).exit_code(), 0);
+    }
+
+    #[test]
+    fn several_targets_use_long_output() {
+        let mut invalid = check(
+            "https://self-signed.example.com",
+            200,
+            DaysRemainingState::Ok,
+        );
+        if let Ok(cert) = &mut invalid.result {
+            cert.findings = vec![Finding::SelfSigned];
+            cert.is_valid = false;
+        }
+        let failed = failed_check(
+            "https://down.example.com",
+            SslCheckError::InternalError("boom".to_string()),
+        );
+        let checks = [
+            check("https://example.com", 45, DaysRemainingState::Ok),
+            check("https://soon.example.com", 20, DaysRemainingState::Warning),
+            invalid,
+            failed,
+        ];
+        assert_eq!(
+            render(&checks),
+            "SSL CRITICAL - 4 targets: 1 critical, 1 warning, 1 unknown, 1 ok \
+             | 'https://example.com days'=45;30;14 'https://soon.example.com days'=20;30;14 \
+             'https://self-signed.example.com days'=200;30;14\n\
+             [OK] https://example.com - 45 days remaining\n\
+             [WARNING] https://soon.example.com - 20 days remaining\n\
+             [CRITICAL] https://self-signed.example.com - self_signed, 200 days remaining\n\
+             [UNKNOWN] https://down.example.com - Internal error: boom\n"
+        );
+        assert_eq!(plugin_state(&checks), PluginState::Critical);
+    }
+}
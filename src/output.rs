@@ -0,0 +1,766 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, Permissions},
+    io::{self, BufWriter, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use tempfile::Builder;
+
+use crate::html_report::write_html;
+use crate::metrics::write_prometheus;
+use crate::report::{CheckRecord, CheckStatus, Report, RunMetadata, SCHEMA_VERSION};
+use crate::targets::TargetOptions;
+use crate::{DaysRemainingState, SslCheck};
+
+// --- Format the results of a run are written in ---
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One human readable line per target
+    #[default]
+    Text,
+    /// A single JSON report document
+    Json,
+    /// One JSON object per target, per line
+    Ndjson,
+    /// A row per target, for spreadsheets
+    Csv,
+    /// A JUnit XML test suite with a test case per target, for CI systems
+    Junit,
+    /// A self-contained HTML page
+    Html,
+    /// A summary and a table in Markdown, for pull requests, wikis and tickets
+    Markdown,
+    /// Gauges in the Prometheus text format, for node_exporter's textfile collector
+    Prometheus,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Junit => write!(f, "junit"),
+            OutputFormat::Html => write!(f, "html"),
+            OutputFormat::Markdown => write!(f, "markdown"),
+            OutputFormat::Prometheus => write!(f, "prometheus"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+// --- Options for the output formats ---
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// Columns written by the CSV format, in order
+    pub csv_columns: Vec<CsvColumn>,
+    /// Whether the CSV format starts with a header row
+    pub csv_header: bool,
+    /// File the results are written to instead of stdout
+    pub path: Option<PathBuf>,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            csv_columns: CsvColumn::value_variants().to_vec(),
+            csv_header: true,
+            path: None,
+        }
+    }
+}
+
+// --- A column of CSV output, named after the matching JSON field ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum CsvColumn {
+    Url,
+    Status,
+    CheckedAt,
+    Attempts,
+    Issuer,
+    Subject,
+    Sans,
+    IsValid,
+    DaysRemaining,
+    DaysRemainingState,
+    NotBefore,
+    NotAfter,
+    Findings,
+    ErrorKind,
+    Severity,
+    Error,
+}
+
+impl CsvColumn {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CsvColumn::Url => "url",
+            CsvColumn::Status => "status",
+            CsvColumn::CheckedAt => "checked_at",
+            CsvColumn::Attempts => "attempts",
+            CsvColumn::Issuer => "issuer",
+            CsvColumn::Subject => "subject",
+            CsvColumn::Sans => "sans",
+            CsvColumn::IsValid => "is_valid",
+            CsvColumn::DaysRemaining => "days_remaining",
+            CsvColumn::DaysRemainingState => "days_remaining_state",
+            CsvColumn::NotBefore => "not_before",
+            CsvColumn::NotAfter => "not_after",
+            CsvColumn::Findings => "findings",
+            CsvColumn::ErrorKind => "error_kind",
+            CsvColumn::Severity => "severity",
+            CsvColumn::Error => "error",
+        }
+    }
+
+    // Certificate columns are left empty for failed checks, and error columns for
+    // completed ones. Lists are joined into a single cell.
+    fn value(&self, record: &CheckRecord) -> String {
+        let cert = record.certificate.as_ref();
+        let error = record.error.as_ref();
+        let timestamp = |time: Option<DateTime<Utc>>| {
+            time.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default()
+        };
+        match self {
+            CsvColumn::Url => record.url.clone(),
+            CsvColumn::Status => record.status.to_string(),
+            CsvColumn::CheckedAt => timestamp(Some(record.checked_at)),
+            CsvColumn::Attempts => record.attempts.to_string(),
+            CsvColumn::Issuer => cert.map(|c| c.issuer.clone()).unwrap_or_default(),
+            CsvColumn::Subject => cert.map(|c| c.subject.clone()).unwrap_or_default(),
+            CsvColumn::Sans => cert
+                .and_then(|c| c.sans.as_ref())
+                .map(|sans| sans.join(LIST_SEPARATOR))
+                .unwrap_or_default(),
+            CsvColumn::IsValid => cert.map(|c| c.is_valid.to_string()).unwrap_or_default(),
+            CsvColumn::DaysRemaining => cert
+                .map(|c| c.days_remaining.to_string())
+                .unwrap_or_default(),
+            CsvColumn::DaysRemainingState => cert
+                .map(|c| match c.days_remaining_state {
+                    DaysRemainingState::Ok => "ok",
+                    DaysRemainingState::Warning => "warning",
+                    DaysRemainingState::Error => "error",
+                })
+                .unwrap_or_default()
+                .to_string(),
+            CsvColumn::NotBefore => timestamp(cert.and_then(|c| c.not_before)),
+            CsvColumn::NotAfter => timestamp(cert.and_then(|c| c.not_after)),
+            CsvColumn::Findings => cert
+                .map(|c| {
+                    c.findings
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(LIST_SEPARATOR)
+                })
+                .unwrap_or_default(),
+            CsvColumn::ErrorKind => error.map(|e| e.kind.to_string()).unwrap_or_default(),
+            CsvColumn::Severity => error.map(|e| e.severity.to_string()).unwrap_or_default(),
+            CsvColumn::Error => error.map(|e| e.message.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+impl FromStr for CsvColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+// Separates the values of multi-valued fields, such as SANs, within a CSV cell
+const LIST_SEPARATOR: &str = "; ";
+
+// Spreadsheets run cells starting with these characters as formulas. Values such as
+// the issuer and the error message come from the server, so they're prefixed with a
+// quote to be shown as text. Plain numbers, such as negative days remaining, are safe.
+fn spreadsheet_safe(value: String) -> String {
+    let is_formula = value.starts_with(['=', '+', '-', '@', '\t', '\r']);
+    if is_formula && value.parse::<f64>().is_err() {
+        format!("'{}", value)
+    } else {
+        value
+    }
+}
+
+// A line of NDJSON output: a result with enough context to stand on its own
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    record: CheckRecord,
+    run: &'a RunMetadata,
+}
+
+/// Writes the results of a run to the file chosen in `options`, or to stdout.
+/// `target_options` supplies the tags of each target for the Prometheus format.
+pub fn write_output(
+    options: &OutputOptions,
+    target_options: &HashMap<String, TargetOptions>,
+    checks: &[SslCheck],
+    run: &RunMetadata,
+) -> io::Result<()> {
+    let write =
+        |mut out: &mut dyn Write| write_results(options, target_options, checks, run, &mut out);
+    match &options.path {
+        Some(path) => write_atomically(path, write),
+        None => write(&mut io::stdout().lock()),
+    }
+}
+
+// Writes to a uniquely named temporary file next to `path` and renames it into place,
+// so readers such as node_exporter never see a partly written file, and overlapping
+// runs don't write to the same temporary file
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // The temporary file is removed if it's dropped before being persisted. It's
+    // created as a new file would be (0666 less the umask) rather than readable only by
+    // its owner, and takes the mode of the file it replaces
+    let existing = fs::metadata(path)
+        .ok()
+        .map(|metadata| metadata.permissions());
+    let temp_file = Builder::new()
+        .permissions(Permissions::from_mode(0o666))
+        .tempfile_in(dir)?;
+    if let Some(permissions) = existing {
+        temp_file.as_file().set_permissions(permissions)?;
+    }
+    let mut file = BufWriter::new(temp_file);
+    write(&mut file)?;
+    let temp_file = file.into_inner().map_err(io::IntoInnerError::into_error)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(path).map_err(|error| error.error)?;
+    Ok(())
+}
+
+/// Writes the results of a run to `out` in the format chosen in `options`.
+pub fn write_results(
+    options: &OutputOptions,
+    target_options: &HashMap<String, TargetOptions>,
+    checks: &[SslCheck],
+    run: &RunMetadata,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    match options.format {
+        OutputFormat::Text => {
+            for check in checks {
+                writeln!(out, "{}", check)?;
+            }
+        }
+        OutputFormat::Json => {
+            let report = Report::new(checks).with_run(run.clone());
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => {
+            for check in checks {
+                let line = NdjsonLine {
+                    schema_version: SCHEMA_VERSION,
+                    record: CheckRecord::from(check),
+                    run,
+                };
+                serde_json::to_writer(&mut *out, &line)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut *out);
+            if options.csv_header {
+                writer.write_record(options.csv_columns.iter().map(CsvColumn::name))?;
+            }
+            for check in checks {
+                let record = CheckRecord::from(check);
+                writer.write_record(
+                    options
+                        .csv_columns
+                        .iter()
+                        .map(|column| spreadsheet_safe(column.value(&record))),
+                )?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Junit => write_junit(&Report::new(checks).with_run(run.clone()), out)?,
+        OutputFormat::Html => write_html(&Report::new(checks).with_run(run.clone()), out)?,
+        OutputFormat::Markdown => write_markdown(&Report::new(checks).with_run(run.clone()), out)?,
+        OutputFormat::Prometheus => {
+            let records: Vec<CheckRecord> = checks.iter().map(CheckRecord::from).collect();
+            write_prometheus(&records, target_options, out)?;
+        }
+    }
+    out.flush()
+}
+
+/// Describes what is wrong with a result: the error for a failed check, otherwise the
+/// findings and how soon the certificate expires. Empty for results that are ok.
+pub fn describe_problems(record: &CheckRecord) -> String {
+    if let Some(error) = &record.error {
+        return error.message.clone();
+    }
+    let Some(cert) = &record.certificate else {
+        return String::new();
+    };
+    let mut problems: Vec<String> = cert.findings.iter().map(ToString::to_string).collect();
+    if cert.days_remaining_state != DaysRemainingState::Ok {
+        problems.push(format!("expires in {} days", cert.days_remaining));
+    }
+    problems.join(", ")
+}
+
+pub fn format_date(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d").to_string()
+}
+
+pub fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn write_markdown(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let summary = &report.summary;
+    writeln!(out, "## SSL Certificate Report")?;
+    writeln!(out)?;
+    let generated_by = match &report.run {
+        Some(run) => format!(" by ssl_checker {}", run.tool_version),
+        None => String::new(),
+    };
+    writeln!(
+        out,
+        "Generated {}{}",
+        format_time(report.generated_at),
+        generated_by
+    )?;
+    writeln!(out)?;
+    let counts: Vec<String> = [
+        (CheckStatus::Ok, summary.ok),
+        (CheckStatus::Warning, summary.warning),
+        (CheckStatus::Error, summary.error),
+        (CheckStatus::Failed, summary.failed),
+    ]
+    .iter()
+    .map(|(status, count)| format!("{} {} {}", status.emoji(), count, status))
+    .collect();
+    writeln!(out, "**{} targets:** {}", summary.total, counts.join(" · "))?;
+    writeln!(out)?;
+
+    writeln!(
+        out,
+        "| Target | Status | Issuer | Expires | Days remaining | Findings |"
+    )?;
+    writeln!(out, "|---|---|---|---|---:|---|")?;
+    for record in &report.results {
+        let cert = record.certificate.as_ref();
+        writeln!(
+            out,
+            "| {} | {} {} | {} | {} | {} | {} |",
+            markdown_cell(&record.url),
+            record.status.emoji(),
+            record.status,
+            markdown_cell(cert.map_or("", |cert| cert.issuer.as_str())),
+            cert.and_then(|cert| cert.not_after)
+                .map(format_date)
+                .unwrap_or_default(),
+            cert.map(|cert| cert.days_remaining.to_string())
+                .unwrap_or_default(),
+            markdown_cell(&describe_problems(record))
+        )?;
+    }
+    Ok(())
+}
+
+// Table cells can't contain pipes or line breaks
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+// Escapes text for an attribute or element, replacing the characters XML 1.0 doesn't
+// allow (such as most control characters) since parsers reject the whole document
+fn xml_text(text: &str) -> String {
+    let allowed: String = text
+        .chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' => c,
+            c if c >= '\u{10000}' => c,
+            _ => char::REPLACEMENT_CHARACTER,
+        })
+        .collect();
+    escape(&allowed).into_owned()
+}
+
+// Each target is a test case: errors and failed checks are failures, and
+// certificates that are only close to expiry are skipped
+fn write_junit(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let summary = &report.summary;
+    let failures = summary.error + summary.failed;
+    let (timestamp, time) = match &report.run {
+        Some(run) => (
+            run.started_at,
+            (run.finished_at - run.started_at).num_milliseconds() as f64 / 1000.0,
+        ),
+        None => (report.generated_at, 0.0),
+    };
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="ssl_checker" tests="{}" failures="{}" errors="0" skipped="{}" time="{:.3}">"#,
+        summary.total, failures, summary.warning, time
+    )?;
+    writeln!(
+        out,
+        r#"  <testsuite name="ssl_checker" tests="{}" failures="{}" errors="0" skipped="{}" time="{:.3}" timestamp="{}">"#,
+        summary.total,
+        failures,
+        summary.warning,
+        time,
+        timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+    )?;
+    for record in &report.results {
+        let name = xml_text(&record.url);
+        let problems = describe_problems(record);
+        let message = xml_text(&problems);
+        let failure_type = match (&record.error, &record.certificate) {
+            (Some(error), _) => error.kind.to_string(),
+            (None, Some(cert)) => match cert.findings.first() {
+                Some(finding) => finding.to_string(),
+                None => "expiry".to_string(),
+            },
+            (None, None) => String::new(),
+        };
+        match record.status {
+            CheckStatus::Ok => writeln!(
+                out,
+                r#"    <testcase name="{}" classname="ssl_checker"/>"#,
+                name
+            )?,
+            CheckStatus::Warning => {
+                writeln!(
+                    out,
+                    r#"    <testcase name="{}" classname="ssl_checker">"#,
+                    name
+                )?;
+                writeln!(out, r#"      <skipped message="{}"/>"#, message)?;
+                writeln!(out, "    </testcase>")?;
+            }
+            CheckStatus::Error | CheckStatus::Failed => {
+                writeln!(
+                    out,
+                    r#"    <testcase name="{}" classname="ssl_checker">"#,
+                    name
+                )?;
+                writeln!(
+                    out,
+                    r#"      <failure message="{}" type="{}">{}</failure>"#,
+                    message,
+                    xml_text(&failure_type),
+                    message
+                )?;
+                writeln!(out, "    </testcase>")?;
+            }
+        }
+    }
+    writeln!(out, "  </testsuite>")?;
+    writeln!(out, "</testsuites>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Finding;
+    use crate::errors::SslCheckError;
+    use crate::test_support::{cert, completed_check, failed_check};
+    use chrono::Utc;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    fn checks() -> Vec<SslCheck> {
+        ["https://a.example.com", "https://b.example.com"]
+            .into_iter()
+            .map(|url| failed_check(url, SslCheckError::NoCertificatesFound(url.to_string())))
+            .collect()
+    }
+
+    fn run() -> RunMetadata {
+        RunMetadata {
+            tool_version: "1.0.0".to_string(),
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            warning_threshold_secs: 30 * 86400,
+            error_threshold_secs: 14 * 86400,
+        }
+    }
+
+    fn write_with(options: &OutputOptions, checks: &[SslCheck]) -> String {
+        let mut out = Vec::new();
+        write_results(options, &HashMap::new(), checks, &run(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn format_options(format: OutputFormat) -> OutputOptions {
+        OutputOptions {
+            format,
+            ..OutputOptions::default()
+        }
+    }
+
+    #[test]
+    fn json_is_a_single_report() {
+        let report: Report =
+            serde_json::from_str(&write_with(&format_options(OutputFormat::Json), &checks()))
+                .unwrap();
+        assert_eq!(report.schema_version, SCHEMA_VERSION);
+        assert_eq!(report.run.unwrap().tool_version, "1.0.0");
+        assert_eq!(report.summary.failed, 2);
+        assert_eq!(report.results.len(), 2);
+    }
+
+    #[test]
+    fn ndjson_is_a_result_per_line() {
+        let output = write_with(&format_options(OutputFormat::Ndjson), &checks());
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["url"], "https://a.example.com");
+        assert_eq!(lines[1]["url"], "https://b.example.com");
+        assert_eq!(lines[1]["schema_version"], SCHEMA_VERSION);
+        assert_eq!(lines[1]["status"], "failed");
+        assert_eq!(lines[1]["error"]["kind"], "no_certificates");
+        assert_eq!(lines[1]["run"]["tool_version"], "1.0.0");
+    }
+
+    #[test]
+    fn csv_keeps_error_rows_and_flattens_lists() {
+        let mut checks = checks();
+        let mut cert = cert(false, 40, DaysRemainingState::Ok);
+        cert.sans = Some(vec![
+            "a.example.com".to_string(),
+            "*.a.example.com".to_string(),
+        ]);
+        cert.findings = vec![Finding::Expired, Finding::SelfSigned];
+        checks[0].result = Ok(cert);
+
+        let options = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_columns: vec![
+                CsvColumn::Url,
+                CsvColumn::Issuer,
+                CsvColumn::Sans,
+                CsvColumn::Findings,
+                CsvColumn::ErrorKind,
+            ],
+            csv_header: true,
+            path: None,
+        };
+        assert_eq!(
+            write_with(&options, &checks),
+            "url,issuer,sans,findings,error_kind\n\
+             https://a.example.com,R11,a.example.com; *.a.example.com,expired; self_signed,\n\
+             https://b.example.com,,,,no_certificates\n"
+        );
+    }
+
+    #[test]
+    fn csv_cells_cannot_start_formulas() {
+        let mut checks = checks();
+        let mut cert = cert(false, -3, DaysRemainingState::Error);
+        cert.issuer = "=HYPERLINK(\"http://evil.example\")".to_string();
+        cert.subject = "@a.example.com".to_string();
+        cert.sans = Some(vec!["+a.example.com".to_string()]);
+        checks[0].result = Ok(cert);
+
+        let options = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_columns: vec![
+                CsvColumn::Issuer,
+                CsvColumn::Subject,
+                CsvColumn::Sans,
+                CsvColumn::DaysRemaining,
+            ],
+            csv_header: false,
+            path: None,
+        };
+        assert_eq!(
+            write_with(&options, &checks[..1]),
+            "\"'=HYPERLINK(\"\"http://evil.example\"\")\",'@a.example.com,'+a.example.com,-3\n"
+        );
+        assert_eq!(
+            spreadsheet_safe("-cmd|' /C calc'!A0".to_string()),
+            "'-cmd|' /C calc'!A0"
+        );
+        assert_eq!(spreadsheet_safe("\tx".to_string()), "'\tx");
+        assert_eq!(spreadsheet_safe("\rx".to_string()), "'\rx");
+    }
+
+    #[test]
+    fn csv_header_is_optional() {
+        let options = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_columns: vec![CsvColumn::Url, CsvColumn::Status],
+            csv_header: false,
+            path: None,
+        };
+        let output = write_with(&options, &checks());
+        assert_eq!(
+            output,
+            "https://a.example.com,failed\nhttps://b.example.com,failed\n"
+        );
+    }
+
+    #[test]
+    fn junit_has_a_test_case_per_target() {
+        let mut checks = checks();
+        let mut cert = cert(true, 20, DaysRemainingState::Warning);
+        checks[0].result = Ok(cert.clone());
+        cert.is_valid = false;
+        cert.findings = vec![Finding::HostnameMismatch];
+        checks.push(completed_check("https://c.example.com/?a=1&b=2", cert));
+
+        let output = write_with(&format_options(OutputFormat::Junit), &checks);
+
+        assert!(output.contains(
+            r#"<testsuite name="ssl_checker" tests="3" failures="2" errors="0" skipped="1""#
+        ));
+        assert!(output.contains(
+            r#"<testcase name="https://a.example.com" classname="ssl_checker">
+      <skipped message="expires in 20 days"/>"#
+        ));
+        assert!(output.contains(
+            r#"<failure message="No SSL certificates found for URL: https://b.example.com" type="no_certificates">"#
+        ));
+        assert!(output.contains(
+            r#"<testcase name="https://c.example.com/?a=1&amp;b=2" classname="ssl_checker">"#
+        ));
+        assert!(output.contains(
+            r#"<failure message="hostname_mismatch, expires in 20 days" type="hostname_mismatch">"#
+        ));
+        assert!(output.trim_end().ends_with("</testsuites>"));
+    }
+
+    #[test]
+    fn junit_replaces_characters_xml_does_not_allow() {
+        let checks = [failed_check(
+            "https://a.example.com/\u{1}",
+            SslCheckError::InternalError("bad\u{0}byte\u{1B}[0m & <tab>\there".to_string()),
+        )];
+        let output = write_with(&format_options(OutputFormat::Junit), &checks);
+        assert!(output.contains("<testcase name=\"https://a.example.com/\u{FFFD}\" "));
+        assert!(output.contains(
+            "message=\"Internal error: bad\u{FFFD}byte\u{FFFD}[0m &amp; &lt;tab&gt;\there\""
+        ));
+        assert!(!output.contains(['\u{0}', '\u{1}', '\u{1B}']));
+    }
+
+    #[test]
+    fn markdown_has_summary_and_table() {
+        let mut checks = checks();
+        let mut cert = cert(true, 45, DaysRemainingState::Ok);
+        cert.issuer = "Issuer | Inc".to_string();
+        cert.not_after = Some("2025-07-16T00:00:00Z".parse().unwrap());
+        checks[0].result = Ok(cert);
+
+        let output = write_with(&format_options(OutputFormat::Markdown), &checks);
+        assert!(output.starts_with("## SSL Certificate Report\n"));
+        assert!(
+            output.contains("**2 targets:** 🟢 1 ok · 🟡 0 warning · 🔴 0 error · ❌ 1 failed\n")
+        );
+        assert!(
+            output.contains(
+                "| https://a.example.com | 🟢 ok | Issuer \\| Inc | 2025-07-16 | 45 |  |\n"
+            )
+        );
+        assert!(output.contains(
+            "| https://b.example.com | ❌ failed |  |  |  | No SSL certificates found for URL: https://b.example.com |\n"
+        ));
+    }
+
+    #[test]
+    fn prometheus_file_is_replaced_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ssl.prom");
+        fs::write(&path, "stale\n").unwrap();
+        let options = OutputOptions {
+            format: OutputFormat::Prometheus,
+            path: Some(path.clone()),
+            ..OutputOptions::default()
+        };
+        let target_options = HashMap::from([(
+            "https://a.example.com".to_string(),
+            TargetOptions {
+                tags: BTreeMap::from([("team".to_string(), "payments".to_string())]),
+                ..Default::default()
+            },
+        )]);
+        write_output(&options, &target_options, &checks(), &run()).unwrap();
+
+        let metrics = fs::read_to_string(&path).unwrap();
+        assert!(metrics.starts_with("# HELP ssl_checker_certificate_expiry_timestamp_seconds "));
+        assert!(metrics.contains(
+            "ssl_checker_check_success{target=\"https://a.example.com\",team=\"payments\"} 0\n"
+        ));
+        assert!(
+            metrics.contains("ssl_checker_check_success{target=\"https://b.example.com\"} 0\n")
+        );
+        // Only the output file is left behind
+        let files: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn out_file_has_the_mode_of_a_new_or_replaced_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        // A file created the usual way gets 0666 less the umask
+        let reference = dir.path().join("reference");
+        fs::write(&reference, "").unwrap();
+
+        let path = dir.path().join("report.html");
+        let options = OutputOptions {
+            format: OutputFormat::Html,
+            path: Some(path.clone()),
+            ..OutputOptions::default()
+        };
+        write_output(&options, &HashMap::new(), &checks(), &run()).unwrap();
+        assert_eq!(mode(&path), mode(&reference));
+
+        fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+        write_output(&options, &HashMap::new(), &checks(), &run()).unwrap();
+        assert_eq!(mode(&path), 0o640);
+    }
+
+    #[test]
+    fn format_names_parse_case_insensitively() {
+        assert_eq!("NDJSON".parse(), Ok(OutputFormat::Ndjson));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+        assert_eq!("days_remaining".parse(), Ok(CsvColumn::DaysRemaining));
+    }
+}
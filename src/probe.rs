@@ -0,0 +1,422 @@
+use std::{
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use hickory_resolver::error::ResolveErrorKind;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, timeout_at};
+use tokio_rustls::TlsConnector;
+use url::{Host, Url};
+
+use crate::errors::{SslCheckError, TimeoutStage};
+
+// Default limits for each stage of a check
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// --- Time limits for checking a single target ---
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    /// Resolving the host and opening the TCP connection, shared between the
+    /// addresses the host resolves to
+    pub connect: Duration,
+    /// The TLS handshake, once connected
+    pub handshake: Duration,
+    /// The whole check, including both of the above
+    pub overall: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: DEFAULT_CONNECT_TIMEOUT,
+            handshake: DEFAULT_HANDSHAKE_TIMEOUT,
+            overall: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Builds a TLS connector that accepts any certificate, so that expired, self-signed
+/// and mismatched certificates can be inspected and reported on rather than rejected.
+pub fn tls_connector() -> Result<TlsConnector, rustls::Error> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Builds a DNS resolver from the system configuration (`/etc/resolv.conf` and the
+/// hosts file), falling back to public resolvers if it can't be read.
+pub fn system_resolver() -> TokioAsyncResolver {
+    TokioAsyncResolver::tokio_from_system_conf().unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to read system DNS configuration - using defaults");
+        TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())
+    })
+}
+
+/// Returns the Mozilla CA certificates bundled with the checker, for use as trust roots.
+pub fn webpki_roots() -> RootCertStore {
+    RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    }
+}
+
+/// Builds a verifier that checks certificate chains against `roots`.
+pub fn chain_verifier(roots: RootCertStore) -> Result<Arc<WebPkiServerVerifier>, rustls::Error> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+        .build()
+        .map_err(|e| rustls::Error::General(e.to_string()))
+}
+
+/// Checks that `certificates` (leaf first) chain up to one of the verifier's trust
+/// roots and are valid for the host in `url`.
+pub fn verify_chain(
+    verifier: &WebPkiServerVerifier,
+    url: &Url,
+    certificates: &[CertificateDer<'static>],
+) -> Result<(), rustls::Error> {
+    let Some((leaf, intermediates)) = certificates.split_first() else {
+        return Err(rustls::Error::NoCertificatesPresented);
+    };
+    let server_name = match url.host() {
+        Some(Host::Domain(domain)) => ServerName::try_from(domain.to_string())
+            .map_err(|e| rustls::Error::General(e.to_string()))?,
+        Some(Host::Ipv4(ip)) => ServerName::from(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => ServerName::from(IpAddr::V6(ip)),
+        None => return Err(rustls::Error::General("URL has no host".to_string())),
+    };
+    verifier
+        .verify_server_cert(leaf, intermediates, &server_name, &[], UnixTime::now())
+        .map(|_| ())
+}
+
+// --- The outcome of a successful TLS handshake ---
+#[derive(Debug)]
+pub struct Handshake {
+    /// The certificate chain presented by the server, leaf certificate first
+    pub certificates: Vec<CertificateDer<'static>>,
+    /// Time taken by the TLS handshake, not including connecting
+    pub duration: Duration,
+}
+
+/// Connects to the host in `url` and returns the certificate chain it presents,
+/// leaf certificate first, along with how long the handshake took.
+pub async fn fetch_certificates(
+    connector: &TlsConnector,
+    resolver: &TokioAsyncResolver,
+    url: &Url,
+    timeouts: &Timeouts,
+) -> Result<Handshake, SslCheckError> {
+    let url_str = url.to_string();
+    let (host, server_name) = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let server_name = ServerName::try_from(domain.to_string()).map_err(|_| {
+                SslCheckError::UrlParseError(
+                    url_str.clone(),
+                    url::ParseError::InvalidDomainCharacter,
+                )
+            })?;
+            (None, server_name)
+        }
+        Some(Host::Ipv4(ip)) => (Some(IpAddr::V4(ip)), ServerName::from(IpAddr::V4(ip))),
+        Some(Host::Ipv6(ip)) => (Some(IpAddr::V6(ip)), ServerName::from(IpAddr::V6(ip))),
+        None => {
+            return Err(SslCheckError::UrlParseError(
+                url_str,
+                url::ParseError::EmptyHost,
+            ));
+        }
+    };
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    // The connect timeout covers resolving the host as well as connecting
+    let connect_deadline = tokio::time::Instant::now() + timeouts.connect;
+    let connect_timeout = |stage, address| SslCheckError::Timeout {
+        stage,
+        limit: timeouts.connect,
+        address,
+    };
+    let addresses: Vec<SocketAddr> = match host {
+        Some(ip) => vec![SocketAddr::new(ip, port)],
+        None => {
+            let domain = server_name.to_str();
+            let dns_error = |error| SslCheckError::DnsError {
+                host: domain.to_string(),
+                error: Box::new(error),
+            };
+            timeout_at(connect_deadline, resolver.lookup_ip(domain.as_ref()))
+                .await
+                .map_err(|_| connect_timeout(TimeoutStage::Dns, None))?
+                .map_err(dns_error)?
+                .iter()
+                .map(|ip| SocketAddr::new(ip, port))
+                .collect()
+        }
+    };
+
+    // Try each address in turn, reporting the last failure if none accept. Each gets
+    // an equal share of what's left of the budget, so an address that never answers
+    // doesn't leave none for the others.
+    let mut connected = None;
+    let mut last_error = None;
+    for (index, address) in addresses.iter().copied().enumerate() {
+        let remaining = connect_deadline.saturating_duration_since(tokio::time::Instant::now());
+        let untried = u32::try_from(addresses.len() - index).unwrap_or(u32::MAX);
+        match timeout(remaining / untried, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => {
+                connected = Some((stream, address));
+                break;
+            }
+            Ok(Err(error)) => last_error = Some(connect_error(address, error)),
+            Err(_) => last_error = Some(connect_timeout(TimeoutStage::Connect, Some(address))),
+        }
+    }
+    let Some((stream, address)) = connected else {
+        return Err(last_error.unwrap_or_else(|| SslCheckError::DnsError {
+            host: server_name.to_str().to_string(),
+            error: Box::new(ResolveErrorKind::Message("no addresses found").into()),
+        }));
+    };
+
+    let handshake_started = Instant::now();
+    let tls_stream = timeout(timeouts.handshake, connector.connect(server_name, stream))
+        .await
+        .map_err(|_| SslCheckError::Timeout {
+            stage: TimeoutStage::Handshake,
+            limit: timeouts.handshake,
+            address: Some(address),
+        })?
+        .map_err(|error| handshake_error(address, error))?;
+
+    let duration = handshake_started.elapsed();
+
+    let (_, connection) = tls_stream.get_ref();
+    let certificates = connection
+        .peer_certificates()
+        .map(|certs| certs.iter().map(|cert| cert.clone().into_owned()).collect())
+        .unwrap_or_default();
+    Ok(Handshake {
+        certificates,
+        duration,
+    })
+}
+
+fn connect_error(address: SocketAddr, error: io::Error) -> SslCheckError {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused => SslCheckError::ConnectionRefused { address },
+        _ => SslCheckError::NetworkError { address, error },
+    }
+}
+
+// TLS failures reach us as io errors wrapping the rustls error
+fn handshake_error(address: SocketAddr, error: io::Error) -> SslCheckError {
+    let tls_error = error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+        .cloned();
+    match tls_error {
+        Some(rustls::Error::AlertReceived(alert)) => SslCheckError::TlsAlert { address, alert },
+        Some(tls_error) => SslCheckError::TlsError {
+            address,
+            error: tls_error,
+        },
+        None => SslCheckError::NetworkError { address, error },
+    }
+}
+
+// Accepts every certificate while still checking the handshake signatures
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl fmt::Debug for AcceptAnyCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AcceptAnyCertificate")
+    }
+}
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::config::{NameServerConfig, Protocol};
+    use tokio::net::TcpListener;
+
+    fn short_timeouts() -> Timeouts {
+        Timeouts {
+            connect: Duration::from_secs(2),
+            handshake: Duration::from_millis(200),
+            overall: Duration::from_secs(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_certificates_times_out_on_silent_server() {
+        // Accepts the connection but never answers the TLS handshake
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _server = tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let url = Url::parse(&format!("https://127.0.0.1:{}", port)).unwrap();
+        let result = fetch_certificates(
+            &tls_connector().unwrap(),
+            &system_resolver(),
+            &url,
+            &short_timeouts(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(SslCheckError::Timeout {
+                stage: TimeoutStage::Handshake,
+                address: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_certificates_reports_slow_dns_as_a_dns_timeout() {
+        // A name server that receives queries but never answers them
+        let name_server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = name_server.local_addr().unwrap();
+        let mut config = ResolverConfig::new();
+        config.add_name_server(NameServerConfig::new(address, Protocol::Udp));
+        let mut options = ResolverOpts::default();
+        options.timeout = Duration::from_secs(30);
+        let resolver = TokioAsyncResolver::tokio(config, options);
+
+        let timeouts = Timeouts {
+            connect: Duration::from_millis(200),
+            ..short_timeouts()
+        };
+        let url = Url::parse("https://dns-timeout.example.com").unwrap();
+        let result =
+            fetch_certificates(&tls_connector().unwrap(), &resolver, &url, &timeouts).await;
+        assert!(matches!(
+            result,
+            Err(SslCheckError::Timeout {
+                stage: TimeoutStage::Dns,
+                address: None,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_certificates_reports_refused_connection() {
+        // Bind then drop a listener to find a port nothing is listening on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let url = Url::parse(&format!("https://127.0.0.1:{}", port)).unwrap();
+        let result = fetch_certificates(
+            &tls_connector().unwrap(),
+            &system_resolver(),
+            &url,
+            &short_timeouts(),
+        )
+        .await;
+        match result {
+            Err(SslCheckError::ConnectionRefused { address }) => assert_eq!(address.port(), port),
+            other => panic!("Expected a refused connection, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_certificates_reports_tls_alert() {
+        // Answers the ClientHello with a fatal handshake_failure alert
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _server = tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut client_hello = [0u8; 512];
+            let _ = socket.read(&mut client_hello).await;
+            socket
+                .write_all(&[0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28])
+                .await
+                .unwrap();
+        });
+
+        let url = Url::parse(&format!("https://127.0.0.1:{}", port)).unwrap();
+        let result = fetch_certificates(
+            &tls_connector().unwrap(),
+            &system_resolver(),
+            &url,
+            &short_timeouts(),
+        )
+        .await;
+        match result {
+            Err(error @ SslCheckError::TlsAlert { .. }) => {
+                assert_eq!(error.alert_code(), Some(40));
+                assert_eq!(error.address().map(|a| a.port()), Some(port));
+            }
+            other => panic!("Expected a TLS alert, got {:?}", other),
+        }
+    }
+}
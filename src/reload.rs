@@ -0,0 +1,174 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+
+use crate::config::{AppConfig, CliArgs, SecretHandling};
+use crate::targets::ConfigSource;
+use crate::validate::Diagnostic;
+
+// --- Reloads the configuration when the files it was built from change ---
+#[derive(Debug)]
+pub struct ConfigReloader {
+    args: CliArgs,
+    // Hash of each watched file's content, or None if it couldn't be read
+    snapshots: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl ConfigReloader {
+    pub fn new(args: CliArgs, config: &AppConfig) -> Self {
+        let mut reloader = Self {
+            args,
+            snapshots: Vec::new(),
+        };
+        reloader.snapshot(config);
+        reloader
+    }
+
+    /// Targets read from stdin can't be read again, so such configs can't be reloaded.
+    pub fn supports_reload(config: &AppConfig) -> bool {
+        !config
+            .sources
+            .urls
+            .values()
+            .any(|source| matches!(source, ConfigSource::Stdin { .. }))
+    }
+
+    /// Returns true if any config file, fragment or URL list has changed on disk.
+    pub fn has_changed(&self) -> bool {
+        self.snapshots
+            .iter()
+            .any(|(path, hash)| hash_file(path) != *hash)
+    }
+
+    /// Loads the configuration again, accepting exactly what startup accepts. On
+    /// failure the caller should keep using its current configuration; the same file
+    /// content won't be reported as changed again.
+    pub fn reload(&mut self) -> Result<AppConfig, Vec<Diagnostic>> {
+        let result = AppConfig::build_reporting_all(self.args.clone(), SecretHandling::Resolve)
+            .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect());
+        match result {
+            Ok(config) => {
+                self.snapshot(&config);
+                Ok(config)
+            }
+            Err(diagnostics) => {
+                self.refresh_hashes();
+                Err(diagnostics)
+            }
+        }
+    }
+
+    fn snapshot(&mut self, config: &AppConfig) {
+        let mut paths = config.sources.files.clone();
+        for source in config.sources.urls.values() {
+            if let ConfigSource::UrlsFile { path, .. } = source
+                && !paths.contains(path)
+            {
+                paths.push(path.clone());
+            }
+        }
+        self.snapshots = paths
+            .into_iter()
+            .map(|path| {
+                let hash = hash_file(&path);
+                (path, hash)
+            })
+            .collect();
+    }
+
+    fn refresh_hashes(&mut self) {
+        for (path, hash) in &mut self.snapshots {
+            *hash = hash_file(path);
+        }
+    }
+}
+
+fn hash_file(path: &PathBuf) -> Option<u64> {
+    let content = std::fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Cli, Command};
+    use clap::Parser;
+    use std::fs;
+
+    fn args_for(path: &std::path::Path) -> CliArgs {
+        let cli = Cli::parse_from(["ssl_checker", "--config-file", path.to_str().unwrap()]);
+        match cli.into_command() {
+            Command::Check(args) => args,
+            _ => panic!("Expected check command"),
+        }
+    }
+
+    #[test]
+    fn reload_picks_up_changed_targets() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "urls = [\"https://a.com\"]\n").unwrap();
+        let args = args_for(&path);
+        let config = AppConfig::build(args.clone()).unwrap();
+        let mut reloader = ConfigReloader::new(args, &config);
+        assert!(!reloader.has_changed());
+
+        fs::write(&path, "urls = [\"https://a.com\", \"https://b.com\"]\n").unwrap();
+        assert!(reloader.has_changed());
+        let config = reloader.reload().unwrap();
+        assert_eq!(config.urls, vec!["https://a.com", "https://b.com"]);
+        assert!(!reloader.has_changed());
+    }
+
+    #[test]
+    fn reload_rejects_invalid_config() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "urls = [\"https://a.com\"]\n").unwrap();
+        let args = args_for(&path);
+        let config = AppConfig::build(args.clone()).unwrap();
+        let mut reloader = ConfigReloader::new(args, &config);
+
+        fs::write(&path, "urls = [\"https://a.com\"]\nconcurrency = 0\n").unwrap();
+        assert!(reloader.has_changed());
+        assert!(reloader.reload().is_err());
+        // The broken content shouldn't trigger another reload attempt
+        assert!(!reloader.has_changed());
+    }
+
+    #[test]
+    fn reload_accepts_an_unchanged_config_that_startup_accepted() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "urls = [\"https://a.com\", \"https://a.com\"]\nwarning_days = 5\nerror_days = 10\n",
+        )
+        .unwrap();
+        let args = args_for(&path);
+        let config = AppConfig::build(args.clone()).unwrap();
+        let mut reloader = ConfigReloader::new(args, &config);
+
+        let reloaded = reloader.reload().unwrap();
+        assert_eq!(reloaded.urls, config.urls);
+        assert_eq!(reloaded.duplicate_urls.len(), 1);
+    }
+
+    #[test]
+    fn reload_watches_urls_files() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "urls_from = \"targets.txt\"\n").unwrap();
+        fs::write(dir.path().join("targets.txt"), "https://a.com\n").unwrap();
+        let args = args_for(&path);
+        let config = AppConfig::build(args.clone()).unwrap();
+        let reloader = ConfigReloader::new(args, &config);
+
+        fs::write(dir.path().join("targets.txt"), "https://b.com\n").unwrap();
+        assert!(reloader.has_changed());
+    }
+}
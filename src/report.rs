@@ -0,0 +1,331 @@
+use std::{fmt, net::SocketAddr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::config::AppConfig;
+use crate::errors::{ErrorKind, Severity, SslCheckError, TimeoutStage, alert_name};
+use crate::exit_code::{EXIT_CHECK_FAILED, EXIT_ERROR, EXIT_OK, EXIT_WARNING};
+use crate::formatter::{GREEN_CIRCLE, RED_CIRCLE, RED_CROSS, YELLOW_CIRCLE};
+use crate::{CertCheckResult, SslCheck};
+
+/// Version of the serialized result schema. It changes whenever a field is removed,
+/// renamed or changes meaning; adding a field doesn't change it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// --- Overall outcome of a check ---
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// Valid and not expiring within the warning threshold
+    Ok,
+    /// Valid, but expiring within the warning threshold
+    Warning,
+    /// Invalid, or expiring within the error threshold
+    Error,
+    /// The check couldn't be completed
+    Failed,
+}
+
+impl CheckStatus {
+    /// The exit code for a run whose worst result has this status
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CheckStatus::Ok => EXIT_OK,
+            CheckStatus::Warning => EXIT_WARNING,
+            CheckStatus::Error => EXIT_ERROR,
+            CheckStatus::Failed => EXIT_CHECK_FAILED,
+        }
+    }
+
+    /// The same symbols the text output uses for each state
+    pub fn emoji(&self) -> char {
+        match self {
+            CheckStatus::Ok => GREEN_CIRCLE,
+            CheckStatus::Warning => YELLOW_CIRCLE,
+            CheckStatus::Error => RED_CIRCLE,
+            CheckStatus::Failed => RED_CROSS,
+        }
+    }
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Ok => write!(f, "ok"),
+            CheckStatus::Warning => write!(f, "warning"),
+            CheckStatus::Error => write!(f, "error"),
+            CheckStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+// --- A set of results, with the schema version and when they were produced ---
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub generated_at: DateTime<Utc>,
+    /// Details of the run that produced the results, when known
+    #[serde(default)]
+    pub run: Option<RunMetadata>,
+    #[serde(default)]
+    pub summary: Summary,
+    pub results: Vec<CheckRecord>,
+}
+
+impl Report {
+    pub fn new(checks: &[SslCheck]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            generated_at: Utc::now(),
+            run: None,
+            summary: Summary::from_checks(checks),
+            results: checks.iter().map(CheckRecord::from).collect(),
+        }
+    }
+
+    pub fn with_run(mut self, run: RunMetadata) -> Self {
+        self.run = Some(run);
+        self
+    }
+}
+
+// --- Details of a run of checks ---
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub tool_version: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub warning_threshold_secs: u64,
+    pub error_threshold_secs: u64,
+}
+
+impl RunMetadata {
+    /// Describes a run that used `config` and started at `started_at`, finishing now.
+    pub fn new(config: &AppConfig, started_at: DateTime<Utc>) -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at,
+            finished_at: Utc::now(),
+            warning_threshold_secs: config.warning_threshold.as_secs(),
+            error_threshold_secs: config.error_threshold.as_secs(),
+        }
+    }
+}
+
+// --- Number of checks with each status ---
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    pub total: usize,
+    pub ok: usize,
+    pub warning: usize,
+    pub error: usize,
+    pub failed: usize,
+}
+
+impl Summary {
+    pub fn from_checks(checks: &[SslCheck]) -> Self {
+        let mut summary = Self {
+            total: checks.len(),
+            ..Self::default()
+        };
+        for check in checks {
+            match check.status() {
+                CheckStatus::Ok => summary.ok += 1,
+                CheckStatus::Warning => summary.warning += 1,
+                CheckStatus::Error => summary.error += 1,
+                CheckStatus::Failed => summary.failed += 1,
+            }
+        }
+        summary
+    }
+}
+
+// --- Serialized form of a single check ---
+/// `SslCheck` serializes as this record, and saved results are read back into it,
+/// since `SslCheck` can't be deserialized itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub url: String,
+    pub status: CheckStatus,
+    pub checked_at: DateTime<Utc>,
+    pub attempts: u32,
+    /// Set when the check completed
+    pub certificate: Option<CertCheckResult>,
+    /// Set when the check failed
+    pub error: Option<ErrorRecord>,
+}
+
+impl From<&SslCheck> for CheckRecord {
+    fn from(check: &SslCheck) -> Self {
+        let (certificate, error) = match &check.result {
+            Ok(cert) => (Some(cert.clone()), None),
+            Err(err) => (None, Some(ErrorRecord::from(err))),
+        };
+        Self {
+            url: check.url.clone(),
+            status: check.status(),
+            checked_at: check.checked_at,
+            attempts: check.attempts,
+            certificate,
+            error,
+        }
+    }
+}
+
+// --- Serialized form of a failed check's error ---
+/// The fields that don't apply to a kind of error are left empty. Deserializing gives
+/// this record rather than the `SslCheckError`, which keeps the underlying error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    pub kind: ErrorKind,
+    pub severity: Severity,
+    pub message: String,
+    /// The address being connected to, if the host had been resolved
+    pub address: Option<SocketAddr>,
+    pub alert_code: Option<u8>,
+    pub alert: Option<String>,
+    pub timeout_stage: Option<TimeoutStage>,
+    pub timeout_secs: Option<u64>,
+}
+
+impl From<&SslCheckError> for ErrorRecord {
+    fn from(error: &SslCheckError) -> Self {
+        let (timeout_stage, timeout_secs) = match error {
+            SslCheckError::Timeout { stage, limit, .. } => (Some(*stage), Some(limit.as_secs())),
+            _ => (None, None),
+        };
+        let alert = match error {
+            SslCheckError::TlsAlert { alert, .. } => Some(alert_name(*alert)),
+            _ => None,
+        };
+        Self {
+            kind: error.kind(),
+            severity: error.severity(),
+            message: error.to_string(),
+            address: error.address(),
+            alert_code: error.alert_code(),
+            alert,
+            timeout_stage,
+            timeout_secs,
+        }
+    }
+}
+
+impl Serialize for SslCheck {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CheckRecord::from(self).serialize(serializer)
+    }
+}
+
+impl Serialize for SslCheckError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ErrorRecord::from(self).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DaysRemainingState;
+    use crate::test_support::{self, cert, failed_check};
+    use rustls::AlertDescription;
+    use serde_json::json;
+
+    fn completed_check() -> SslCheck {
+        let mut cert = cert(true, 20, DaysRemainingState::Warning);
+        cert.sans = Some(vec![
+            "example.com".to_string(),
+            "www.example.com".to_string(),
+        ]);
+        cert.not_before = Some("2025-04-22T00:00:00Z".parse().unwrap());
+        cert.not_after = Some("2025-06-21T23:59:59Z".parse().unwrap());
+        cert.handshake_duration_ms = Some(42);
+        let mut check = test_support::completed_check("https://example.com", cert);
+        check.checked_at = "2025-06-01T12:00:00Z".parse().unwrap();
+        check
+    }
+
+    #[test]
+    fn completed_check_serializes_to_schema() {
+        assert_eq!(
+            serde_json::to_value(completed_check()).unwrap(),
+            json!({
+                "url": "https://example.com",
+                "status": "warning",
+                "checked_at": "2025-06-01T12:00:00Z",
+                "attempts": 1,
+                "certificate": {
+                    "issuer": "R11",
+                    "subject": "example.com",
+                    "sans": ["example.com", "www.example.com"],
+                    "is_valid": true,
+                    "days_remaining": 20,
+                    "days_remaining_state": "warning",
+                    "not_before": "2025-04-22T00:00:00Z",
+                    "not_after": "2025-06-21T23:59:59Z",
+                    "findings": [],
+                    "chain": [],
+                    "handshake_duration_ms": 42
+                },
+                "error": null
+            })
+        );
+    }
+
+    #[test]
+    fn failed_check_serializes_error_fields() {
+        let check = failed_check(
+            "https://example.com",
+            SslCheckError::TlsAlert {
+                address: "192.0.2.10:443".parse().unwrap(),
+                alert: AlertDescription::HandshakeFailure,
+            },
+        );
+        let value = serde_json::to_value(&check).unwrap();
+        assert_eq!(value["status"], "failed");
+        assert_eq!(value["certificate"], json!(null));
+        assert_eq!(
+            value["error"],
+            json!({
+                "kind": "tls_alert",
+                "severity": "critical",
+                "message": "TLS handshake alert from 192.0.2.10:443: handshake_failure (40)",
+                "address": "192.0.2.10:443",
+                "alert_code": 40,
+                "alert": "handshake_failure",
+                "timeout_stage": null,
+                "timeout_secs": null
+            })
+        );
+    }
+
+    #[test]
+    fn serialized_checks_deserialize_as_records() {
+        let mut failed = failed_check(
+            "https://down.example.com",
+            SslCheckError::Timeout {
+                stage: TimeoutStage::Handshake,
+                limit: std::time::Duration::from_secs(10),
+                address: Some("192.0.2.10:443".parse().unwrap()),
+            },
+        );
+        failed.attempts = 3;
+        for check in [completed_check(), failed] {
+            let json = serde_json::to_string(&check).unwrap();
+            let record: CheckRecord = serde_json::from_str(&json).unwrap();
+            assert_eq!(record, CheckRecord::from(&check));
+        }
+    }
+
+    #[test]
+    fn report_round_trips() {
+        let report = Report::new(&[completed_check()]);
+        assert_eq!(report.schema_version, SCHEMA_VERSION);
+        assert_eq!(report.summary.warning, 1);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+// Default retry settings for failed checks
+pub const DEFAULT_RETRIES: u32 = 2;
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+// --- How failed checks are retried ---
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub retries: u32,
+    /// Delay before the first retry, doubling before each further retry
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_RETRY_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before making attempt number `attempt + 1`, or None if
+    /// `attempt` attempts have already used up the retries.
+    pub fn delay_after(&self, attempt: u32) -> Option<Duration> {
+        if attempt > self.retries {
+            return None;
+        }
+        let exponent = attempt.saturating_sub(1).min(16);
+        Some(self.backoff.saturating_mul(1 << exponent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_after_doubles_until_retries_run_out() {
+        let policy = RetryPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        };
+        assert_eq!(policy.delay_after(1), Some(Duration::from_millis(500)));
+        assert_eq!(policy.delay_after(2), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay_after(3), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay_after(4), None);
+    }
+
+    #[test]
+    fn no_retries_means_a_single_attempt() {
+        let policy = RetryPolicy {
+            retries: 0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay_after(1), None);
+    }
+}
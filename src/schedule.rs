@@ -0,0 +1,179 @@
+use std::{fmt, time::Duration};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+
+use crate::duration::HumanDuration;
+
+// Delay before retrying after a failed run, doubling with each consecutive failure
+const FAILURE_BACKOFF_BASE: Duration = Duration::from_secs(30);
+const FAILURE_BACKOFF_MAX: Duration = Duration::from_secs(60 * 60);
+
+// --- When daemon mode runs the checks ---
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// Run at a fixed interval from the previous run
+    Interval(Duration),
+    /// Run at the times matched by a cron expression, evaluated in `timezone`
+    Cron {
+        expression: String,
+        cron: Box<Cron>,
+        timezone: Tz,
+    },
+}
+
+impl Schedule {
+    /// Parses a standard 5-field cron expression (or 6 fields with seconds first).
+    pub fn cron(expression: &str, timezone: Tz) -> Result<Self, String> {
+        let cron = Cron::new(expression)
+            .with_seconds_optional()
+            .parse()
+            .map_err(|e| format!("invalid cron expression '{}': {}", expression, e))?;
+        let schedule = Schedule::Cron {
+            expression: expression.to_string(),
+            cron: Box::new(cron),
+            timezone,
+        };
+
+        // Expressions such as "0 0 31 2 *" parse but never match
+        if schedule.next_run(None, Utc::now()).is_none() {
+            return Err(format!("cron expression '{}' never matches", expression));
+        }
+        Ok(schedule)
+    }
+
+    /// Returns when the next run should start, given when the last one started.
+    /// Intervals are measured from the last run (or from `now` before the first run),
+    /// and a run that is already overdue is due at `now`.
+    pub fn next_run(
+        &self,
+        last_run: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Interval(interval) => {
+                let interval = chrono::Duration::from_std(*interval).ok()?;
+                let next = last_run.unwrap_or(now).checked_add_signed(interval)?;
+                Some(next.max(now))
+            }
+            Schedule::Cron { cron, timezone, .. } => cron
+                .find_next_occurrence(&now.with_timezone(timezone), false)
+                .ok()
+                .map(|next| next.with_timezone(&Utc)),
+        }
+    }
+
+    pub fn timezone(&self) -> Option<Tz> {
+        match self {
+            Schedule::Interval(_) => None,
+            Schedule::Cron { timezone, .. } => Some(*timezone),
+        }
+    }
+}
+
+/// Returns how long to wait before retrying after `consecutive_failures` failed runs
+/// in a row. Callers should still use the regular next run if it comes sooner.
+pub fn failure_backoff(consecutive_failures: u32) -> Duration {
+    let exponent = consecutive_failures.saturating_sub(1).min(16);
+    FAILURE_BACKOFF_BASE
+        .saturating_mul(1 << exponent)
+        .min(FAILURE_BACKOFF_MAX)
+}
+
+impl PartialEq for Schedule {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Schedule::Interval(a), Schedule::Interval(b)) => a == b,
+            (
+                Schedule::Cron {
+                    expression: a,
+                    timezone: a_tz,
+                    ..
+                },
+                Schedule::Cron {
+                    expression: b,
+                    timezone: b_tz,
+                    ..
+                },
+            ) => a == b && a_tz == b_tz,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Interval(interval) => write!(f, "every {}", HumanDuration(*interval)),
+            Schedule::Cron {
+                expression,
+                timezone,
+                ..
+            } => write!(f, "cron '{}' ({})", expression, timezone),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn interval_next_run_is_anchored_to_last_run() {
+        let schedule = Schedule::Interval(Duration::from_secs(60 * 60));
+        let now = Utc.with_ymd_and_hms(2025, 6, 2, 10, 30, 0).unwrap();
+        let last_run = Utc.with_ymd_and_hms(2025, 6, 2, 10, 0, 0).unwrap();
+
+        assert_eq!(
+            schedule.next_run(Some(last_run), now),
+            Some(Utc.with_ymd_and_hms(2025, 6, 2, 11, 0, 0).unwrap())
+        );
+        assert_eq!(
+            schedule.next_run(None, now),
+            Some(Utc.with_ymd_and_hms(2025, 6, 2, 11, 30, 0).unwrap())
+        );
+        // Overdue runs happen straight away
+        let old_run = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(schedule.next_run(Some(old_run), now), Some(now));
+    }
+
+    #[test]
+    fn cron_next_run_uses_timezone() {
+        let schedule = Schedule::cron("0 8 * * MON-FRI", chrono_tz::Europe::London).unwrap();
+        // Friday 2025-06-06 09:00 London time (BST, UTC+1)
+        let now = Utc.with_ymd_and_hms(2025, 6, 6, 8, 0, 0).unwrap();
+
+        // Next weekday 08:00 London time is Monday 07:00 UTC
+        assert_eq!(
+            schedule.next_run(None, now),
+            Some(Utc.with_ymd_and_hms(2025, 6, 9, 7, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn cron_numeric_weekdays_follow_standard_cron() {
+        // 1-5 is Monday to Friday, as in standard cron
+        let schedule = Schedule::cron("30 6 * * 1-5", chrono_tz::UTC).unwrap();
+        let saturday = Utc.with_ymd_and_hms(2025, 6, 7, 12, 0, 0).unwrap();
+        assert_eq!(
+            schedule.next_run(None, saturday),
+            Some(Utc.with_ymd_and_hms(2025, 6, 9, 6, 30, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn failure_backoff_doubles_up_to_max() {
+        assert_eq!(failure_backoff(1), Duration::from_secs(30));
+        assert_eq!(failure_backoff(2), Duration::from_secs(60));
+        assert_eq!(failure_backoff(3), Duration::from_secs(120));
+        assert_eq!(failure_backoff(100), FAILURE_BACKOFF_MAX);
+    }
+
+    #[test]
+    fn cron_rejects_invalid_expressions() {
+        assert!(Schedule::cron("not a cron", chrono_tz::UTC).is_err());
+        assert!(Schedule::cron("0 0 31 2 *", chrono_tz::UTC).is_err());
+    }
+}
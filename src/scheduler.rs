@@ -0,0 +1,331 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::AppConfig;
+use crate::schedule::{Schedule, failure_backoff};
+
+// --- Tracks when each target is next due in daemon mode ---
+#[derive(Debug)]
+pub struct Scheduler {
+    targets: Vec<ScheduledTarget>,
+    rng: fastrand::Rng,
+}
+
+#[derive(Debug)]
+struct ScheduledTarget {
+    url: String,
+    schedule: Schedule,
+    jitter: Duration,
+    // When the last run was scheduled for, before jitter was added. Intervals are
+    // measured from here so the jitter doesn't accumulate from run to run.
+    last_scheduled: Option<DateTime<Utc>>,
+    next_scheduled: Option<DateTime<Utc>>,
+    next_run: Option<DateTime<Utc>>,
+    // When a failed run is retried, kept apart from the regular runs so a retry
+    // doesn't move them
+    retry_at: Option<DateTime<Utc>>,
+    consecutive_failures: u32,
+}
+
+impl ScheduledTarget {
+    // The regular run or the retry, whichever comes first
+    fn due_at(&self) -> Option<DateTime<Utc>> {
+        match (self.next_run, self.retry_at) {
+            (Some(next_run), Some(retry_at)) => Some(next_run.min(retry_at)),
+            (next_run, retry_at) => next_run.or(retry_at),
+        }
+    }
+}
+
+impl Scheduler {
+    /// Schedules every target in the configuration. Targets are due straight away
+    /// (plus jitter) if `run_on_start` is set.
+    pub fn new(config: &AppConfig, now: DateTime<Utc>) -> Self {
+        let mut scheduler = Self {
+            targets: Vec::new(),
+            rng: fastrand::Rng::new(),
+        };
+        scheduler.update(config, now);
+        scheduler
+    }
+
+    /// Applies a reloaded configuration. Targets that are still present keep their
+    /// place in the schedule unless their schedule or jitter changed, in which case
+    /// they are rescheduled from their last run. New targets are scheduled as on startup.
+    pub fn update(&mut self, config: &AppConfig, now: DateTime<Utc>) {
+        let mut previous = std::mem::take(&mut self.targets);
+
+        for url in &config.urls {
+            let Some(schedule) = config.schedule_for(url) else {
+                continue;
+            };
+            let jitter = config.jitter_for(url);
+
+            let target = match previous.iter().position(|target| target.url == *url) {
+                Some(idx) => {
+                    let mut target = previous.swap_remove(idx);
+                    if target.schedule != schedule || target.jitter != jitter {
+                        target.schedule = schedule;
+                        target.jitter = jitter;
+                        let next = target.schedule.next_run(target.last_scheduled, now);
+                        self.set_next(&mut target, next);
+                    }
+                    target
+                }
+                None => {
+                    let mut target = ScheduledTarget {
+                        url: url.clone(),
+                        schedule,
+                        jitter,
+                        last_scheduled: None,
+                        next_scheduled: None,
+                        next_run: None,
+                        retry_at: None,
+                        consecutive_failures: 0,
+                    };
+                    let next = if config.run_on_start {
+                        Some(now)
+                    } else {
+                        target.schedule.next_run(None, now)
+                    };
+                    self.set_next(&mut target, next);
+                    target
+                }
+            };
+            self.targets.push(target);
+        }
+    }
+
+    /// The earliest time any target is due, or None if no target will run again.
+    pub fn next_run(&self) -> Option<DateTime<Utc>> {
+        self.targets
+            .iter()
+            .filter_map(ScheduledTarget::due_at)
+            .min()
+    }
+
+    /// Whether any target's scheduled time has passed but it is still waiting out its
+    /// jitter. Results are held back until then, so the targets of a period are
+    /// reported together rather than batch by batch.
+    pub fn has_delayed_targets(&self, now: DateTime<Utc>) -> bool {
+        self.targets.iter().any(|target| {
+            target
+                .next_scheduled
+                .is_some_and(|scheduled| scheduled <= now)
+                && target.next_run.is_some_and(|next_run| next_run > now)
+        })
+    }
+
+    /// Returns the targets that are due at `now`, in configuration order, and
+    /// schedules their following run.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        let mut targets = std::mem::take(&mut self.targets);
+        let mut due = Vec::new();
+
+        for target in &mut targets {
+            if target.next_run.is_some_and(|next_run| next_run <= now) {
+                // The regular run takes the place of any retry that was waiting
+                target.retry_at = None;
+                target.last_scheduled = target.next_scheduled;
+                let next = target.schedule.next_run(target.last_scheduled, now);
+                self.set_next(target, next);
+                due.push(target.url.clone());
+            } else if target.retry_at.is_some_and(|retry_at| retry_at <= now) {
+                target.retry_at = None;
+                due.push(target.url.clone());
+            }
+        }
+
+        self.targets = targets;
+        due
+    }
+
+    pub fn record_success(&mut self, urls: &[String]) {
+        for target in self.targets_mut(urls) {
+            target.consecutive_failures = 0;
+        }
+    }
+
+    /// Retries the given targets after a backoff, unless their next regular run comes
+    /// sooner anyway. Regular runs stay on their schedule. Returns the backoff used.
+    pub fn record_failure(&mut self, urls: &[String], now: DateTime<Utc>) -> Duration {
+        let mut longest = Duration::ZERO;
+        for target in self.targets_mut(urls) {
+            target.consecutive_failures += 1;
+            let backoff = failure_backoff(target.consecutive_failures);
+            let retry_at = now + backoff;
+            target.retry_at = target
+                .next_run
+                .is_none_or(|next_run| retry_at < next_run)
+                .then_some(retry_at);
+            longest = longest.max(backoff);
+        }
+        longest
+    }
+
+    fn targets_mut<'a>(
+        &'a mut self,
+        urls: &'a [String],
+    ) -> impl Iterator<Item = &'a mut ScheduledTarget> {
+        self.targets
+            .iter_mut()
+            .filter(|target| urls.contains(&target.url))
+    }
+
+    fn set_next(&mut self, target: &mut ScheduledTarget, next: Option<DateTime<Utc>>) {
+        target.next_scheduled = next;
+        target.next_run = next.map(|next| next + self.random_jitter(target.jitter));
+    }
+
+    fn random_jitter(&mut self, jitter: Duration) -> Duration {
+        if jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        let millis = u64::try_from(jitter.as_millis()).unwrap_or(u64::MAX);
+        Duration::from_millis(self.rng.u64(0..=millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigSources;
+    use crate::duration::HumanDuration;
+    use crate::probe::Timeouts;
+    use crate::retry::RetryPolicy;
+    use crate::targets::TargetOptions;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn daemon_config(urls: &[&str]) -> AppConfig {
+        AppConfig {
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            error_threshold: HumanDuration::from_days(14).into(),
+            warning_threshold: HumanDuration::from_days(30).into(),
+            log_level: "info".to_string(),
+            schedule: Some(Schedule::Interval(24 * HOUR)),
+            run_on_start: true,
+            jitter: None,
+            target_options: HashMap::new(),
+            timeouts: Timeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            concurrency: 50,
+            per_host_concurrency: 2,
+            output: Default::default(),
+            fail_on: Default::default(),
+            mode: Default::default(),
+            metrics_address: None,
+            slack_webhook_url: None,
+            duplicate_urls: Vec::new(),
+            sources: ConfigSources::default(),
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 2, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn targets_run_on_their_own_intervals() {
+        let mut config = daemon_config(&["https://critical.com", "https://other.com"]);
+        config.target_options.insert(
+            "https://critical.com".to_string(),
+            TargetOptions {
+                interval: Some(HOUR),
+                ..Default::default()
+            },
+        );
+        let mut scheduler = Scheduler::new(&config, start());
+
+        assert_eq!(
+            scheduler.take_due(start()),
+            vec!["https://critical.com", "https://other.com"]
+        );
+        assert_eq!(scheduler.next_run(), Some(start() + HOUR));
+        assert_eq!(
+            scheduler.take_due(start() + HOUR),
+            vec!["https://critical.com"]
+        );
+        assert!(scheduler.take_due(start() + HOUR).is_empty());
+        assert_eq!(
+            scheduler.take_due(start() + 24 * HOUR),
+            vec!["https://critical.com", "https://other.com"]
+        );
+    }
+
+    #[test]
+    fn jitter_delays_runs_within_the_limit() {
+        let mut config = daemon_config(&["https://a.com", "https://b.com", "https://c.com"]);
+        config.jitter = Some(HOUR);
+        let mut scheduler = Scheduler::new(&config, start());
+
+        for target in &scheduler.targets {
+            let next_run = target.next_run.unwrap();
+            assert!(next_run >= start() && next_run <= start() + HOUR);
+        }
+        // Intervals are measured from the scheduled time, not the jittered one
+        let due = scheduler.take_due(start() + HOUR);
+        assert_eq!(due.len(), 3);
+        for target in &scheduler.targets {
+            assert_eq!(target.next_scheduled, Some(start() + 24 * HOUR));
+        }
+    }
+
+    #[test]
+    fn targets_waiting_out_jitter_are_delayed() {
+        let mut config = daemon_config(&["https://a.com", "https://b.com"]);
+        config.jitter = Some(HOUR);
+        let mut scheduler = Scheduler::new(&config, start());
+        scheduler.targets[0].next_run = Some(start());
+        scheduler.targets[1].next_run = Some(start() + HOUR / 2);
+
+        assert_eq!(scheduler.take_due(start()), vec!["https://a.com"]);
+        assert!(scheduler.has_delayed_targets(start()));
+
+        let now = start() + HOUR / 2;
+        assert_eq!(scheduler.take_due(now), vec!["https://b.com"]);
+        assert!(!scheduler.has_delayed_targets(now));
+    }
+
+    #[test]
+    fn failed_runs_are_retried_after_backoff() {
+        let config = daemon_config(&["https://a.com"]);
+        let mut scheduler = Scheduler::new(&config, start());
+        let due = scheduler.take_due(start());
+
+        let backoff = scheduler.record_failure(&due, start());
+        assert_eq!(backoff, failure_backoff(1));
+        assert_eq!(scheduler.next_run(), Some(start() + backoff));
+
+        let due = scheduler.take_due(start() + backoff);
+        assert_eq!(due, vec!["https://a.com"]);
+        scheduler.record_success(&due);
+        assert_eq!(scheduler.targets[0].consecutive_failures, 0);
+
+        // The retry doesn't move the regular schedule
+        assert_eq!(scheduler.next_run(), Some(start() + 24 * HOUR));
+        assert!(scheduler.take_due(start() + 24 * HOUR - HOUR).is_empty());
+        assert_eq!(
+            scheduler.take_due(start() + 24 * HOUR),
+            vec!["https://a.com"]
+        );
+        assert_eq!(scheduler.next_run(), Some(start() + 48 * HOUR));
+    }
+
+    #[test]
+    fn update_keeps_existing_targets_in_place() {
+        let config = daemon_config(&["https://a.com"]);
+        let mut scheduler = Scheduler::new(&config, start());
+        scheduler.take_due(start());
+
+        let mut config = daemon_config(&["https://a.com", "https://b.com"]);
+        config.run_on_start = false;
+        scheduler.update(&config, start() + HOUR);
+
+        assert_eq!(scheduler.targets[0].next_run, Some(start() + 24 * HOUR));
+        assert_eq!(scheduler.targets[1].next_run, Some(start() + 25 * HOUR));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fmt, process::Command};
+
+use crate::errors::ConfigError;
+
+// Prefixes for settings that reference a secret stored elsewhere
+const ENV_PREFIX: &str = "env:";
+const FILE_PREFIX: &str = "file:";
+const CMD_PREFIX: &str = "cmd:";
+
+const REDACTED: &str = "[REDACTED]";
+
+// --- Secret value that is never written to logs ---
+#[derive(Clone, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Returns the underlying value. Only use this where the secret is actually needed.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+/// Resolves a secret-valued setting.
+///
+/// Values of the form `env:NAME`, `file:/path` and `cmd:command` are read from the
+/// environment variable, file contents or command output respectively. Any other
+/// value is used as-is.
+pub fn resolve_secret(
+    setting: &str,
+    value: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<Secret, ConfigError> {
+    let resolve_error =
+        |reason: String| ConfigError::SecretResolveError(setting.to_string(), reason);
+
+    let resolved = if let Some(name) = value.strip_prefix(ENV_PREFIX) {
+        env(name)
+            .ok_or_else(|| resolve_error(format!("environment variable {} is not set", name)))?
+    } else if let Some(path) = value.strip_prefix(FILE_PREFIX) {
+        std::fs::read_to_string(path)
+            .map_err(|e| resolve_error(format!("failed to read file {}: {}", path, e)))?
+    } else if let Some(command) = value.strip_prefix(CMD_PREFIX) {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .output()
+            .map_err(|e| resolve_error(format!("failed to run command: {}", e)))?;
+        if !output.status.success() {
+            return Err(resolve_error(format!(
+                "command exited with {}",
+                output.status
+            )));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|_| resolve_error("command output is not valid UTF-8".to_string()))?
+    } else {
+        return Ok(Secret::new(value));
+    };
+
+    // Files and command output usually end with a newline
+    let resolved = resolved.trim();
+    if resolved.is_empty() {
+        return Err(resolve_error("resolved value is empty".to_string()));
+    }
+    Ok(Secret::new(resolved))
+}
+
+/// Whether a setting's value is a reference to a secret stored elsewhere.
+pub fn is_secret_reference(value: &str) -> bool {
+    [ENV_PREFIX, FILE_PREFIX, CMD_PREFIX]
+        .iter()
+        .any(|prefix| value.starts_with(prefix))
+}
+
+/// Checks the syntax of a secret-valued setting without resolving it, so no
+/// variables are read, no files opened and no commands run. The value is returned
+/// as it is.
+pub fn check_secret_reference(setting: &str, value: &str) -> Result<Secret, ConfigError> {
+    let invalid = |reason: &str| {
+        Err(ConfigError::InvalidValue(
+            setting.to_string(),
+            reason.to_string(),
+        ))
+    };
+
+    if let Some(name) = value.strip_prefix(ENV_PREFIX) {
+        if name.is_empty() || name.contains(['=', '\0']) || name.contains(char::is_whitespace) {
+            return invalid("env: must be followed by an environment variable name");
+        }
+    } else if let Some(path) = value.strip_prefix(FILE_PREFIX) {
+        if path.trim().is_empty() {
+            return invalid("file: must be followed by a path");
+        }
+    } else if let Some(command) = value.strip_prefix(CMD_PREFIX)
+        && command.trim().is_empty()
+    {
+        return invalid("cmd: must be followed by a command");
+    }
+    Ok(Secret::new(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn secret_is_redacted_in_debug_and_display() {
+        let secret = Secret::new("https://hooks.slack.com/services/T000/B000/XXXX");
+        assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+        assert_eq!(format!("{}", secret), "[REDACTED]");
+        assert_eq!(format!("{:?}", Some(secret)), "Some(Secret([REDACTED]))");
+    }
+
+    #[test]
+    fn resolve_secret_plain_value() {
+        let secret = resolve_secret("slack_webhook_url", "https://slack.com", &no_env).unwrap();
+        assert_eq!(secret.expose(), "https://slack.com");
+    }
+
+    #[test]
+    fn check_secret_reference_does_not_resolve() {
+        for value in [
+            "env:UNSET_WEBHOOK",
+            "file:/does/not/exist",
+            "cmd:exit 1",
+            "plain",
+        ] {
+            let secret = check_secret_reference("slack_webhook_url", value).unwrap();
+            assert_eq!(secret.expose(), value);
+        }
+        for value in ["env:", "env:TWO WORDS", "file: ", "cmd:"] {
+            let result = check_secret_reference("slack_webhook_url", value);
+            assert!(matches!(result, Err(ConfigError::InvalidValue(..))));
+        }
+        assert!(is_secret_reference("cmd:pass show slack"));
+        assert!(!is_secret_reference("https://hooks.slack.com/services/x"));
+    }
+
+    #[test]
+    fn resolve_secret_from_env() {
+        let env = |name: &str| (name == "WEBHOOK").then(|| "https://env.slack.com".to_string());
+        let secret = resolve_secret("slack_webhook_url", "env:WEBHOOK", &env).unwrap();
+        assert_eq!(secret.expose(), "https://env.slack.com");
+
+        let result = resolve_secret("slack_webhook_url", "env:MISSING", &env);
+        assert!(matches!(result, Err(ConfigError::SecretResolveError(..))));
+    }
+
+    #[test]
+    fn resolve_secret_from_file() {
+        let mut file = NamedTempFile::new().expect("Failed to create temp file");
+        writeln!(file, "https://file.slack.com").expect("Failed to write to temp file");
+        let reference = format!("file:{}", file.path().display());
+
+        let secret = resolve_secret("slack_webhook_url", &reference, &no_env).unwrap();
+        assert_eq!(secret.expose(), "https://file.slack.com");
+    }
+
+    #[test]
+    fn resolve_secret_from_command() {
+        let secret = resolve_secret(
+            "slack_webhook_url",
+            "cmd:echo https://cmd.slack.com",
+            &no_env,
+        )
+        .unwrap();
+        assert_eq!(secret.expose(), "https://cmd.slack.com");
+
+        let result = resolve_secret("slack_webhook_url", "cmd:exit 1", &no_env);
+        assert!(matches!(result, Err(ConfigError::SecretResolveError(..))));
+    }
+}
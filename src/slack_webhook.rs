@@ -1,7 +1,11 @@
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
 use reqwest::{self, Client};
 use serde_json::json;
 
 use crate::SslCheck;
+use crate::errors::{Severity, SslCheckError};
 
 // Build Functions to fire off slack webhook for notifications
 async fn send_slack_notification(
@@ -13,7 +17,13 @@ async fn send_slack_notification(
     });
 
     let client = Client::new();
-    let res = client.post(endpoint).json(&payload).send().await?;
+    // Strip the webhook URL from errors so the secret doesn't end up in logs
+    let res = client
+        .post(endpoint)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| e.without_url())?;
 
     if res.status().is_success() {
         tracing::info!("Slack notification sent successfully");
@@ -24,23 +34,41 @@ async fn send_slack_notification(
     Ok(())
 }
 
-pub async fn send_check_results(slack_endpoint: &str, results: &[SslCheck]) {
-    //get current date time
-    let now = chrono::Utc::now();
-
-    // Construct Message
+// Failed checks are listed first, most severe first, and critical failures are called
+// out at the top so they stand out from transient problems
+fn format_check_results(results: &[SslCheck], now: DateTime<Utc>) -> String {
     let mut message = format!(
         "SSL Checker Utility Report -  Date: {} (UTC)\n\n",
         now.format("%Y-%m-%d %H:%M:%S")
     );
 
-    let result_lines = results
+    let severity = |check: &SslCheck| check.result.as_ref().err().map(SslCheckError::severity);
+    let critical = results
+        .iter()
+        .filter(|check| severity(check) == Some(Severity::Critical))
+        .count();
+    if critical > 0 {
+        message.push_str(&format!(
+            ":rotating_light: {} critical {} - clients can't establish a TLS connection\n\n",
+            critical,
+            if critical == 1 { "failure" } else { "failures" }
+        ));
+    }
+
+    let mut sorted: Vec<&SslCheck> = results.iter().collect();
+    sorted.sort_by_key(|check| Reverse(severity(check)));
+    let result_lines = sorted
         .iter()
         .map(|result| format!("{result}"))
         .collect::<Vec<String>>()
         .join("\n");
 
     message.push_str(&result_lines);
+    message
+}
+
+pub async fn send_check_results(slack_endpoint: &str, results: &[SslCheck]) {
+    let message = format_check_results(results, Utc::now());
 
     match send_slack_notification(slack_endpoint, &message).await {
         Ok(()) => {}
@@ -49,3 +77,59 @@ pub async fn send_check_results(slack_endpoint: &str, results: &[SslCheck]) {
         }
     };
 }
+
+pub async fn send_run_failure(slack_endpoint: &str, error: &str) {
+    let now = Utc::now();
+    let message = format!(
+        "SSL Checker Utility Report -  Date: {} (UTC)\n\nSSL checks failed to run: {}",
+        now.format("%Y-%m-%d %H:%M:%S"),
+        error
+    );
+
+    if let Err(err) = send_slack_notification(slack_endpoint, &message).await {
+        tracing::error!("Error sending Slack notification: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DaysRemainingState;
+    use crate::errors::TimeoutStage;
+    use crate::test_support::{cert, completed_check, failed_check};
+    use rustls::AlertDescription;
+
+    #[test]
+    fn critical_failures_are_called_out_and_listed_first() {
+        let address = "192.0.2.1:443".parse().unwrap();
+        let results = [
+            completed_check(
+                "https://ok.example.com",
+                cert(true, 45, DaysRemainingState::Ok),
+            ),
+            failed_check(
+                "https://slow.example.com",
+                SslCheckError::Timeout {
+                    stage: TimeoutStage::Handshake,
+                    limit: std::time::Duration::from_secs(10),
+                    address: Some(address),
+                },
+            ),
+            failed_check(
+                "https://alert.example.com",
+                SslCheckError::TlsAlert {
+                    address,
+                    alert: AlertDescription::HandshakeFailure,
+                },
+            ),
+        ];
+
+        let message = format_check_results(&results, Utc::now());
+        assert!(message.contains(
+            "\n\n:rotating_light: 1 critical failure - clients can't establish a TLS connection\n\n"
+        ));
+        let position = |url| message.find(url).unwrap();
+        assert!(position("alert.example.com") < position("slow.example.com"));
+        assert!(position("slow.example.com") < position("ok.example.com"));
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::errors::ConfigError;
+use crate::probe::Timeouts;
+use crate::retry::RetryPolicy;
+
+// Value of `--urls-from` that reads the target list from stdin
+pub const STDIN_MARKER: &str = "-";
+
+// --- Where a target or setting was loaded from ---
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    CommandLine,
+    Environment { name: String },
+    Stdin { line: usize },
+    UrlsFile { path: PathBuf, line: usize },
+    ConfigFile { path: PathBuf, line: Option<usize> },
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::CommandLine => write!(f, "command line"),
+            ConfigSource::Environment { name } => write!(f, "environment variable {}", name),
+            ConfigSource::Stdin { line } => write!(f, "stdin:{}", line),
+            ConfigSource::UrlsFile { path, line } => write!(f, "{}:{}", path.display(), line),
+            ConfigSource::ConfigFile {
+                path,
+                line: Some(line),
+            } => write!(f, "{}:{}", path.display(), line),
+            ConfigSource::ConfigFile { path, line: None } => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// --- Settings for a single target from a `[[targets]]` table ---
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetOptions {
+    /// Check this target on its own interval instead of the global schedule
+    pub interval: Option<Duration>,
+    /// Overrides the global jitter for this target
+    pub jitter: Option<Duration>,
+    /// Override the global time limits for this target
+    pub connect_timeout: Option<Duration>,
+    pub handshake_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    /// Overrides the global retry count for this target
+    pub retries: Option<u32>,
+    /// Labels added to this target's metrics, such as its team or environment
+    pub tags: BTreeMap<String, String>,
+}
+
+impl TargetOptions {
+    /// Applies this target's overrides to the default time limits.
+    pub fn timeouts(&self, defaults: Timeouts) -> Timeouts {
+        Timeouts {
+            connect: self.connect_timeout.unwrap_or(defaults.connect),
+            handshake: self.handshake_timeout.unwrap_or(defaults.handshake),
+            overall: self.timeout.unwrap_or(defaults.overall),
+        }
+    }
+
+    /// Applies this target's retry count to the default retry policy.
+    pub fn retry_policy(&self, defaults: RetryPolicy) -> RetryPolicy {
+        RetryPolicy {
+            retries: self.retries.unwrap_or(defaults.retries),
+            ..defaults
+        }
+    }
+}
+
+// --- A target that was listed more than once ---
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateUrl {
+    pub url: String,
+    pub first_source: ConfigSource,
+    pub duplicate_source: ConfigSource,
+}
+
+impl fmt::Display for DuplicateUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Duplicate URL '{}' at {} (first defined at {})",
+            self.url, self.duplicate_source, self.first_source
+        )
+    }
+}
+
+/// Parses a newline-delimited list of URLs, skipping blank lines and `#` comments.
+/// Returns each URL along with its 1-based line number.
+pub fn parse_url_list(content: &str) -> Vec<(usize, String)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| (line_no, line.to_string()))
+        .collect()
+}
+
+/// Loads targets from a newline-delimited file, or from stdin when `path` is `-`.
+pub fn load_url_list(path: &Path) -> Result<Vec<(String, ConfigSource)>, ConfigError> {
+    if path == Path::new(STDIN_MARKER) {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| ConfigError::FileReadError(path.to_path_buf(), Box::new(e)))?;
+        return Ok(parse_url_list(&content)
+            .into_iter()
+            .map(|(line, url)| (url, ConfigSource::Stdin { line }))
+            .collect());
+    }
+
+    if !path.exists() {
+        return Err(ConfigError::FileNotFound(path.to_path_buf()));
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::FileReadError(path.to_path_buf(), Box::new(e)))?;
+
+    Ok(parse_url_list(&content)
+        .into_iter()
+        .map(|(line, url)| {
+            let source = ConfigSource::UrlsFile {
+                path: path.to_path_buf(),
+                line,
+            };
+            (url, source)
+        })
+        .collect())
+}
+
+/// Finds the 1-based line on which `url` appears as a quoted TOML string for the
+/// `occurrence`th time (counting from 0), so each repeat of a URL has its own line.
+pub fn find_url_line(content: &str, url: &str, occurrence: usize) -> Option<usize> {
+    let needle = format!("\"{}\"", url);
+    let mut remaining = occurrence;
+    for (idx, line) in content.lines().enumerate() {
+        let matches = line.matches(&needle).count();
+        if matches > remaining {
+            return Some(idx + 1);
+        }
+        remaining -= matches;
+    }
+    None
+}
+
+/// Removes repeated URLs, keeping the first occurrence, and reports each repeat.
+pub fn dedup_targets(
+    targets: Vec<(String, ConfigSource)>,
+) -> (Vec<(String, ConfigSource)>, Vec<DuplicateUrl>) {
+    let mut seen: HashMap<String, ConfigSource> = HashMap::new();
+    let mut unique = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for (url, source) in targets {
+        if let Some(first_source) = seen.get(&url) {
+            duplicates.push(DuplicateUrl {
+                url,
+                first_source: first_source.clone(),
+                duplicate_source: source,
+            });
+        } else {
+            seen.insert(url.clone(), source.clone());
+            unique.push((url, source));
+        }
+    }
+
+    (unique, duplicates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_url_list_skips_blanks_and_comments() {
+        let content = "https://a.com\n\n# comment\n  https://b.com  \n";
+        let urls = parse_url_list(content);
+        assert_eq!(
+            urls,
+            vec![
+                (1, "https://a.com".to_string()),
+                (4, "https://b.com".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn dedup_targets_keeps_first_and_reports_repeats() {
+        let targets = vec![
+            ("https://a.com".to_string(), ConfigSource::CommandLine),
+            ("https://b.com".to_string(), ConfigSource::CommandLine),
+            ("https://a.com".to_string(), ConfigSource::Stdin { line: 3 }),
+        ];
+        let (unique, duplicates) = dedup_targets(targets);
+        let urls: Vec<_> = unique.iter().map(|(url, _)| url.as_str()).collect();
+        assert_eq!(urls, vec!["https://a.com", "https://b.com"]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].url, "https://a.com");
+        assert_eq!(duplicates[0].first_source, ConfigSource::CommandLine);
+        assert_eq!(
+            duplicates[0].duplicate_source,
+            ConfigSource::Stdin { line: 3 }
+        );
+    }
+
+    #[test]
+    fn find_url_line_locates_quoted_url() {
+        let content = "log_level = \"info\"\nurls = [\n  \"https://a.com\",\n]\n";
+        assert_eq!(find_url_line(content, "https://a.com", 0), Some(3));
+        assert_eq!(find_url_line(content, "https://b.com", 0), None);
+    }
+
+    #[test]
+    fn find_url_line_locates_each_repeat() {
+        let content = "urls = [\"https://a.com\", \"https://a.com\"]\n\n[[targets]]\nurl = \"https://a.com\"\n";
+        assert_eq!(find_url_line(content, "https://a.com", 0), Some(1));
+        assert_eq!(find_url_line(content, "https://a.com", 1), Some(1));
+        assert_eq!(find_url_line(content, "https://a.com", 2), Some(4));
+        assert_eq!(find_url_line(content, "https://a.com", 3), None);
+    }
+}
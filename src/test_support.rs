@@ -0,0 +1,41 @@
+//! Fixtures shared by the unit tests.
+
+use chrono::Utc;
+
+use crate::errors::SslCheckError;
+use crate::{CertCheckResult, DaysRemainingState, SslCheck};
+
+/// A certificate for example.com issued by R11, with the other details left empty.
+pub(crate) fn cert(
+    is_valid: bool,
+    days_remaining: i64,
+    state: DaysRemainingState,
+) -> CertCheckResult {
+    CertCheckResult::new(
+        "R11".to_string(),
+        "example.com".to_string(),
+        is_valid,
+        days_remaining,
+        state,
+    )
+}
+
+/// A check of `url` that completed on the first attempt just now.
+pub(crate) fn completed_check(url: &str, cert: CertCheckResult) -> SslCheck {
+    SslCheck {
+        url: url.to_string(),
+        result: Ok(cert),
+        attempts: 1,
+        checked_at: Utc::now(),
+    }
+}
+
+/// A check of `url` that failed on the first attempt just now.
+pub(crate) fn failed_check(url: &str, error: SslCheckError) -> SslCheck {
+    SslCheck {
+        url: url.to_string(),
+        result: Err(error),
+        attempts: 1,
+        checked_at: Utc::now(),
+    }
+}
@@ -0,0 +1,476 @@
+use std::{fmt, str::FromStr};
+
+use tracing_subscriber::filter::LevelFilter;
+use url::Url;
+
+use crate::config::{AppConfig, CliArgs, LocatedConfigError, SecretHandling};
+use crate::duration::HumanDuration;
+use crate::errors::ConfigError;
+use crate::schedule::Schedule;
+use crate::secret::is_secret_reference;
+use crate::targets::ConfigSource;
+
+// --- A problem found while validating the configuration ---
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub source: Option<ConfigSource>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(source: Option<&ConfigSource>, message: String) -> Self {
+        Self {
+            source: source.cloned(),
+            message,
+        }
+    }
+}
+
+impl From<LocatedConfigError> for Diagnostic {
+    fn from(located: LocatedConfigError) -> Self {
+        // The source already says where a parse error is, so only its reason is kept
+        let message = match &located.error {
+            ConfigError::TomlParseError(_, err) if located.source.is_some() => {
+                format!("Invalid TOML: {}", err.message())
+            }
+            error => error.to_string(),
+        };
+        Self {
+            source: located.source,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}: {}", source, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Loads the configuration and runs the semantic checks against it. `secrets` says
+/// whether secret references are resolved or only checked.
+pub fn validate(args: CliArgs, secrets: SecretHandling) -> Result<AppConfig, Vec<Diagnostic>> {
+    let config = AppConfig::build_reporting_all(args, secrets)
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+
+    let diagnostics = check_config(&config);
+    if diagnostics.is_empty() {
+        Ok(config)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Runs semantic checks that parsing alone can't catch.
+pub fn check_config(config: &AppConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_thresholds(config, &mut diagnostics);
+    check_daemon_settings(config, &mut diagnostics);
+    check_urls(config, &mut diagnostics);
+    check_notifiers(config, &mut diagnostics);
+    diagnostics
+}
+
+fn check_thresholds(config: &AppConfig, diagnostics: &mut Vec<Diagnostic>) {
+    let settings = &config.sources.settings;
+
+    if config.warning_threshold <= config.error_threshold {
+        let source = settings
+            .get("warning_days")
+            .or_else(|| settings.get("error_days"));
+        diagnostics.push(Diagnostic::new(
+            source,
+            format!(
+                "warning_days ({}) must be greater than error_days ({})",
+                HumanDuration(config.warning_threshold),
+                HumanDuration(config.error_threshold)
+            ),
+        ));
+    }
+}
+
+fn check_daemon_settings(config: &AppConfig, diagnostics: &mut Vec<Diagnostic>) {
+    let settings = &config.sources.settings;
+
+    if let Some(Schedule::Interval(interval)) = &config.schedule
+        && interval.is_zero()
+    {
+        diagnostics.push(Diagnostic::new(
+            settings.get("check_frequency"),
+            "check_frequency must be greater than zero".to_string(),
+        ));
+    }
+    for url in &config.urls {
+        let Some(interval) = config.target_options.get(url).and_then(|o| o.interval) else {
+            continue;
+        };
+        let source = config.sources.urls.get(url);
+        if config.schedule.is_none() {
+            diagnostics.push(Diagnostic::new(
+                source,
+                format!(
+                    "interval for '{}' has no effect unless check_frequency or schedule is set",
+                    url
+                ),
+            ));
+        } else if interval.is_zero() {
+            diagnostics.push(Diagnostic::new(
+                source,
+                format!("interval for '{}' must be greater than zero", url),
+            ));
+        }
+    }
+    if config.metrics_address.is_some() && config.schedule.is_none() {
+        diagnostics.push(Diagnostic::new(
+            settings.get("metrics_address"),
+            "metrics_address has no effect unless check_frequency or schedule is set".to_string(),
+        ));
+    }
+    let global_timeouts = [
+        ("connect_timeout", config.timeouts.connect),
+        ("handshake_timeout", config.timeouts.handshake),
+        ("timeout", config.timeouts.overall),
+    ];
+    for (key, timeout) in global_timeouts {
+        if timeout.is_zero() {
+            diagnostics.push(Diagnostic::new(
+                settings.get(key),
+                format!("{} must be greater than zero", key),
+            ));
+        }
+    }
+    for (url, options) in &config.target_options {
+        let target_timeouts = [
+            ("connect_timeout", options.connect_timeout),
+            ("handshake_timeout", options.handshake_timeout),
+            ("timeout", options.timeout),
+        ];
+        for (key, timeout) in target_timeouts {
+            if timeout.is_some_and(|timeout| timeout.is_zero()) {
+                diagnostics.push(Diagnostic::new(
+                    config.sources.urls.get(url),
+                    format!("{} for '{}' must be greater than zero", key, url),
+                ));
+            }
+        }
+    }
+    for (key, limit) in [
+        ("concurrency", config.concurrency),
+        ("per_host_concurrency", config.per_host_concurrency),
+    ] {
+        if limit == 0 {
+            diagnostics.push(Diagnostic::new(
+                settings.get(key),
+                format!("{} must be greater than zero", key),
+            ));
+        }
+    }
+    if LevelFilter::from_str(&config.log_level).is_err() {
+        diagnostics.push(Diagnostic::new(
+            settings.get("log_level"),
+            format!(
+                "log_level '{}' is not one of off, error, warn, info, debug or trace",
+                config.log_level
+            ),
+        ));
+    }
+}
+
+fn check_urls(config: &AppConfig, diagnostics: &mut Vec<Diagnostic>) {
+    for url in &config.urls {
+        let source = config.sources.urls.get(url);
+        match Url::parse(url) {
+            Ok(parsed) if parsed.scheme() != "https" => diagnostics.push(Diagnostic::new(
+                source,
+                format!("URL '{}' must use the https scheme", url),
+            )),
+            Ok(parsed) if parsed.host_str().is_none_or(str::is_empty) => diagnostics.push(
+                Diagnostic::new(source, format!("URL '{}' has no host", url)),
+            ),
+            Ok(_) => {}
+            Err(e) => diagnostics.push(Diagnostic::new(
+                source,
+                format!("Invalid URL '{}': {}", url, e),
+            )),
+        }
+    }
+
+    for duplicate in &config.duplicate_urls {
+        diagnostics.push(Diagnostic::new(
+            Some(&duplicate.duplicate_source),
+            format!(
+                "Duplicate URL '{}' (first defined at {})",
+                duplicate.url, duplicate.first_source
+            ),
+        ));
+    }
+}
+
+// Never include secret values in the messages. References to secrets aren't resolved
+// while validating, so only literal URLs are checked.
+fn check_notifiers(config: &AppConfig, diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(webhook_url) = config
+        .slack_webhook_url
+        .as_ref()
+        .filter(|webhook_url| !is_secret_reference(webhook_url.expose()))
+    {
+        let source = config.sources.settings.get("slack_webhook_url");
+        match Url::parse(webhook_url.expose()) {
+            Ok(parsed) if parsed.scheme() != "https" => diagnostics.push(Diagnostic::new(
+                source,
+                "slack_webhook_url must use the https scheme".to_string(),
+            )),
+            Ok(_) => {}
+            Err(e) => diagnostics.push(Diagnostic::new(
+                source,
+                format!("slack_webhook_url is not a valid URL: {}", e),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Cli, Command, ConfigSources};
+    use crate::probe::Timeouts;
+    use crate::retry::RetryPolicy;
+    use crate::secret::Secret;
+    use crate::targets::{DuplicateUrl, TargetOptions};
+    use clap::Parser;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn valid_config() -> AppConfig {
+        AppConfig {
+            urls: vec!["https://example.com".to_string()],
+            error_threshold: HumanDuration::from_days(14).into(),
+            warning_threshold: HumanDuration::from_days(30).into(),
+            log_level: "info".to_string(),
+            schedule: None,
+            run_on_start: false,
+            jitter: None,
+            target_options: Default::default(),
+            timeouts: Timeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            concurrency: 50,
+            per_host_concurrency: 2,
+            output: Default::default(),
+            fail_on: Default::default(),
+            mode: Default::default(),
+            metrics_address: None,
+            slack_webhook_url: None,
+            duplicate_urls: Vec::new(),
+            sources: ConfigSources::default(),
+        }
+    }
+
+    #[test]
+    fn check_config_valid() {
+        assert!(check_config(&valid_config()).is_empty());
+    }
+
+    #[test]
+    fn check_config_warning_below_error_days() {
+        let mut config = valid_config();
+        config.warning_threshold = HumanDuration::from_days(10).into();
+        let source = ConfigSource::ConfigFile {
+            path: PathBuf::from("config.toml"),
+            line: Some(4),
+        };
+        config.sources.settings.insert("warning_days", source);
+
+        let diagnostics = check_config(&config);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "config.toml:4: warning_days (10d) must be greater than error_days (14d)"
+        );
+    }
+
+    #[test]
+    fn check_config_invalid_urls() {
+        let mut config = valid_config();
+        config.urls = vec![
+            "not a url".to_string(),
+            "http://example.com".to_string(),
+            "https://example.com".to_string(),
+        ];
+        let source = ConfigSource::UrlsFile {
+            path: PathBuf::from("targets.txt"),
+            line: 2,
+        };
+        config
+            .sources
+            .urls
+            .insert("http://example.com".to_string(), source);
+
+        let diagnostics = check_config(&config);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(
+            diagnostics[0]
+                .message
+                .starts_with("Invalid URL 'not a url'")
+        );
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "targets.txt:2: URL 'http://example.com' must use the https scheme"
+        );
+    }
+
+    #[test]
+    fn check_config_duplicate_urls() {
+        let mut config = valid_config();
+        config.duplicate_urls.push(DuplicateUrl {
+            url: "https://example.com".to_string(),
+            first_source: ConfigSource::CommandLine,
+            duplicate_source: ConfigSource::Stdin { line: 3 },
+        });
+
+        let diagnostics = check_config(&config);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "stdin:3: Duplicate URL 'https://example.com' (first defined at command line)"
+        );
+    }
+
+    #[test]
+    fn check_config_target_interval_needs_daemon_mode() {
+        let mut config = valid_config();
+        config.target_options.insert(
+            "https://example.com".to_string(),
+            TargetOptions {
+                interval: Some(std::time::Duration::from_secs(3600)),
+                ..Default::default()
+            },
+        );
+
+        let diagnostics = check_config(&config);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("has no effect"));
+
+        config.schedule = Some(Schedule::Interval(std::time::Duration::from_secs(86400)));
+        assert!(check_config(&config).is_empty());
+    }
+
+    #[test]
+    fn check_config_daemon_and_notifier_settings() {
+        let mut config = valid_config();
+        config.schedule = Some(Schedule::Interval(std::time::Duration::ZERO));
+        config.log_level = "verbose".to_string();
+        config.slack_webhook_url = Some(Secret::new("http://hooks.slack.com/secret"));
+        config.per_host_concurrency = 0;
+
+        let diagnostics = check_config(&config);
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics.iter().all(|d| !d.message.contains("secret")));
+    }
+
+    #[test]
+    fn check_config_metrics_settings() {
+        let mut config = valid_config();
+        config.metrics_address = Some("127.0.0.1:9115".parse().unwrap());
+
+        let diagnostics = check_config(&config);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .message
+                .starts_with("metrics_address has no effect")
+        );
+
+        config.schedule = Some(Schedule::Interval(std::time::Duration::from_secs(86400)));
+        assert!(check_config(&config).is_empty());
+    }
+
+    // Runs validate-config against a config file
+    fn validate_file(content: &str) -> (Result<AppConfig, Vec<Diagnostic>>, String) {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "{}", content).unwrap();
+        let path = file.path().display().to_string();
+        let cli = Cli::parse_from(["ssl_checker", "validate-config", "--config-file", &path]);
+        match cli.into_command() {
+            Command::ValidateConfig(args) => (validate(args, SecretHandling::CheckSyntax), path),
+            _ => panic!("Expected validate-config command"),
+        }
+    }
+
+    #[test]
+    fn validate_reports_every_build_error_with_its_line() {
+        let (result, path) = validate_file(
+            r#"urls = ["https://example.com"]
+timezone = "Mars/Olympus"
+concurrency = 0
+slack_webhook_url = "cmd:exit 1"
+
+[[targets]]
+url = "https://tagged.example.com"
+tags = { __name__ = "up" }
+"#,
+        );
+        let diagnostics: Vec<String> = result
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                format!(
+                    "{}:2: Invalid schedule: unknown timezone 'Mars/Olympus'",
+                    path
+                ),
+                format!(
+                    "{}:3: Invalid value for 'concurrency': must be at least 1",
+                    path
+                ),
+                format!(
+                    "{}:7: Invalid tag '__name__' for 'https://tagged.example.com': tag names must be valid metric label names (letters, digits and underscores, not starting with a digit or '__') other than 'target'",
+                    path
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_does_not_resolve_secrets() {
+        let (result, _) = validate_file(
+            r#"urls = ["https://example.com"]
+slack_webhook_url = "env:SSL_CHECKER_TEST_UNSET_WEBHOOK"
+"#,
+        );
+        assert!(result.is_ok());
+
+        let (result, path) = validate_file(
+            r#"urls = ["https://example.com"]
+slack_webhook_url = "env:"
+"#,
+        );
+        assert_eq!(
+            result.unwrap_err()[0].to_string(),
+            format!(
+                "{}:2: Invalid value for 'slack_webhook_url': env: must be followed by an environment variable name",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn validate_reports_the_line_of_a_toml_error() {
+        let (result, path) = validate_file("urls = [\n  \"https://example.com\",\n  oops\n]\n");
+        let diagnostics = result.unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .starts_with(&format!("{}:3: Invalid TOML: ", path)),
+            "{}",
+            diagnostics[0]
+        );
+    }
+}
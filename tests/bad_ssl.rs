@@ -1,14 +1,34 @@
-use ssl_checker::{config, run};
+use ssl_checker::{
+    checker::Checker,
+    config,
+    duration::HumanDuration,
+    probe::{Timeouts, webpki_roots},
+    retry::RetryPolicy,
+    run,
+};
 
 // Some Defaults
 fn default_config_with_url(url: &str) -> config::AppConfig {
     config::AppConfig {
         urls: vec![url.to_string()],
-        error_days: 15,
-        warning_days: 30,
+        error_threshold: HumanDuration::from_days(15).into(),
+        warning_threshold: HumanDuration::from_days(30).into(),
         log_level: "info".to_string(),
-        check_frequency: None,
+        schedule: None,
+        run_on_start: false,
+        jitter: None,
+        target_options: Default::default(),
+        timeouts: Timeouts::default(),
+        retry_policy: RetryPolicy::default(),
+        concurrency: 50,
+        per_host_concurrency: 2,
+        output: Default::default(),
+        fail_on: Default::default(),
+        mode: Default::default(),
+        metrics_address: None,
         slack_webhook_url: None,
+        duplicate_urls: Vec::new(),
+        sources: config::ConfigSources::default(),
     }
 }
 
@@ -26,7 +46,7 @@ async fn expired_cert() {
     let check = &result[0];
     assert!(check.result.is_ok());
     let check_result = check.result.as_ref().unwrap();
-    assert_eq!(check_result.is_valid, false);
+    assert!(!check_result.is_valid);
     assert_eq!(check_result.days_remaining, 0);
     assert_eq!(check.url, "https://expired.badssl.com/".to_string());
 }
@@ -46,7 +66,7 @@ async fn wrong_host() {
     let check = &result[0];
     assert!(check.result.is_ok());
     let check_result = check.result.as_ref().unwrap();
-    assert_eq!(check_result.is_valid, false);
+    assert!(!check_result.is_valid);
     assert_eq!(check.url, "https://wrong.host.badssl.com/".to_string());
 }
 
@@ -65,7 +85,7 @@ async fn self_signed() {
     let check = &result[0];
     assert!(check.result.is_ok());
     let check_result = check.result.as_ref().unwrap();
-    assert_eq!(check_result.is_valid, false);
+    assert!(!check_result.is_valid);
     assert_eq!(check.url, "https://self-signed.badssl.com/".to_string());
 }
 
@@ -84,6 +104,26 @@ async fn self_signed() {
 //     let check = &result[0];
 //     assert!(check.result.is_ok());
 //     let check_result = check.result.as_ref().unwrap();
-//     assert_eq!(check_result.is_valid, false);
+//     assert!(!check_result.is_valid);
 //     assert_eq!(check.url, "https://untrusted-root.badssl.com/".to_string());
 // }
+
+#[tokio::test]
+async fn untrusted_root_with_trust_roots() {
+    // Arrange
+    let checker = Checker::builder()
+        .trust_roots(webpki_roots())
+        .build()
+        .unwrap();
+
+    //Act
+    let check = checker
+        .check_one("https://untrusted-root.badssl.com/")
+        .await;
+
+    // Assert
+    assert!(check.result.is_ok());
+    let check_result = check.result.as_ref().unwrap();
+    assert!(!check_result.is_valid);
+    assert_eq!(check.url, "https://untrusted-root.badssl.com/".to_string());
+}